@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+// The PY32F002B has no DMA: each conversion raises an interrupt, which moves the sample into
+// the ring buffer. Slow sample times keep the interrupt rate manageable.
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::adc::{Adc, SampleTime};
+use py32_hal::peripherals::ADC1;
+use py32_hal::{adc, bind_interrupts};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    ADC_COMP => adc::InterruptHandler<ADC1>;
+});
+
+const ADC_BUF_SIZE: usize = 64;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let adc = Adc::new(p.ADC1, Irqs);
+    let mut vrefint = adc.enable_vref();
+    let mut pin = p.PA6;
+
+    static mut ADC_BUF: [u16; ADC_BUF_SIZE] = [0; ADC_BUF_SIZE];
+    let mut adc = adc.into_ring_buffered(unsafe { &mut *core::ptr::addr_of_mut!(ADC_BUF) });
+    adc.set_sample_time(SampleTime::CYCLES239_5);
+    // Converted in ascending channel number, so VREFINT (channel 9) follows PA6.
+    adc.enable_channel(&mut pin);
+    adc.enable_channel(&mut vrefint);
+
+    let mut measurements = [0u16; ADC_BUF_SIZE / 2];
+    loop {
+        match adc.read(&mut measurements).await {
+            Ok(_) => {
+                let (mut pa6, mut vref) = (0u32, 0u32);
+                for pair in measurements.chunks_exact(2) {
+                    pa6 += u32::from(pair[0]);
+                    vref += u32::from(pair[1]);
+                }
+                info!("PA6: {} mV", pa6 * adc::VREF_INT / vref.max(1));
+            }
+            Err(e) => warn!("Error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::usart::{Config, Uart};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    info!("Hello World!");
+
+    // PA2 and PB6 are SWD pins, so reusing them may lock you out of programming.
+    // Refer to the `unsafe-reuse-swd-pins` feature's comments in py32-hal/Cargo.toml.
+
+    let p = py32_hal::init(Default::default());
+
+    // No DMA on this chip: bytes are moved by the USART interrupt.
+    let config = Config::default();
+    let mut usart = Uart::new(p.USART1, p.PA7, p.PA6, Irqs, config).unwrap();
+
+    unwrap!(usart.write(b"Hello Embassy World!\r\n").await);
+    info!("wrote Hello, starting echo");
+
+    let mut buf = [0u8; 32];
+    loop {
+        let n = unwrap!(usart.read_until_idle(&mut buf).await);
+        unwrap!(usart.write(&buf[..n]).await);
+    }
+}
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use py32_hal::adc::{Adc, AdcChannel, SampleTime};
+use py32_hal::peripherals::ADC1;
+use py32_hal::{adc, bind_interrupts};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    ADC_COMP => adc::InterruptHandler<ADC1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let mut adc = Adc::new(p.ADC1, Irqs);
+    adc.set_sample_time(SampleTime::CYCLES71_5);
+    let mut dma = p.DMA1_CH1;
+
+    let mut channels = [
+        p.PA4.degrade_adc(),
+        p.PA0.degrade_adc(),
+        p.PA1.degrade_adc(),
+        adc.enable_vref().degrade_adc(),
+    ];
+    let mut readings = [0u16; 4];
+
+    loop {
+        adc.read_sequence(&mut channels, &mut readings, dma.reborrow())
+            .await;
+        info!("PA4, PA0, PA1, VREFINT: {}", readings);
+        Timer::after_millis(100).await;
+    }
+}
@@ -0,0 +1,41 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use py32_hal::adc::{Adc, SampleTime, WatchdogChannels};
+use py32_hal::gpio::{Level, Output, Speed};
+use py32_hal::peripherals::ADC1;
+use py32_hal::{adc, bind_interrupts};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    ADC_COMP => adc::InterruptHandler<ADC1>;
+});
+
+/// Current sense amplifier output above which the load is switched off.
+const OVER_CURRENT: u16 = 3000;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let mut adc = Adc::new(p.ADC1, Irqs);
+    adc.set_sample_time(SampleTime::CYCLES71_5);
+    let mut sense = p.PA1;
+    let mut load = Output::new(p.PB1, Level::High, Speed::Low);
+
+    info!("Current: {}", adc.read(&mut sense).await);
+    adc.set_watchdog(WatchdogChannels::from_channel(&sense), 0, OVER_CURRENT);
+
+    loop {
+        adc.wait_for_out_of_window().await;
+        load.set_low();
+        warn!("Over-current, load off");
+
+        Timer::after_secs(1).await;
+        load.set_high();
+    }
+}
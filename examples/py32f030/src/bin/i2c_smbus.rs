@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_time::Timer;
+use py32_hal::exti::ExtiInput;
+use py32_hal::gpio::Pull;
+use py32_hal::i2c::smbus::{Pec, Smbus};
+use py32_hal::i2c::I2c;
+use py32_hal::time::Hertz;
+use py32_hal::{bind_interrupts, i2c, peripherals};
+use {defmt_rtt as _, panic_probe as _};
+
+/// Smart battery, see the Smart Battery Data Specification.
+const BATTERY: u8 = 0x0B;
+const VOLTAGE: u8 = 0x09;
+const DEVICE_NAME: u8 = 0x21;
+
+bind_interrupts!(struct Irqs {
+    I2C1 => i2c::GlobalInterruptHandler<peripherals::I2C1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    info!("Hello world!");
+    let p = py32_hal::init(Default::default());
+
+    let i2c = I2c::new(
+        p.I2C1,
+        p.PA3,
+        p.PA2,
+        Irqs,
+        p.DMA1_CH2,
+        p.DMA1_CH1,
+        Hertz(100_000),
+        Default::default(),
+    )
+    .unwrap();
+    let mut smbus = Smbus::new(i2c, Pec::Software);
+    let mut alert = ExtiInput::new(p.PB5, p.EXTI5, Pull::Up);
+
+    let mut name = [0; 32];
+    match smbus.block_read(BATTERY, DEVICE_NAME, &mut name).await {
+        Ok(n) => info!("Battery: {=[u8]:a}", name[..n]),
+        Err(e) => error!("SMBus error: {:?}", e),
+    }
+
+    loop {
+        match select(smbus.wait_for_alert(&mut alert), Timer::after_secs(1)).await {
+            Either::First(Ok(addr)) => info!("Alert from {:#x}", addr),
+            Either::First(Err(e)) => error!("SMBus error: {:?}", e),
+            Either::Second(()) => match smbus.read_word(BATTERY, VOLTAGE).await {
+                Ok(mv) => info!("Voltage: {} mV", mv),
+                Err(e) => error!("SMBus error: {:?}", e),
+            },
+        }
+    }
+}
@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+//! I2C co-processor: a register file the controller writes with `[reg, data...]` and reads
+//! back with a write of `[reg]` followed by a read.
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::i2c::{I2c, SlaveAddrConfig, SlaveCommandKind};
+use py32_hal::time::Hertz;
+use py32_hal::{bind_interrupts, i2c, peripherals};
+use {defmt_rtt as _, panic_probe as _};
+
+const ADDRESS: u8 = 0x42;
+
+bind_interrupts!(struct Irqs {
+    I2C1 => i2c::GlobalInterruptHandler<peripherals::I2C1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    info!("Hello world!");
+    let p = py32_hal::init(Default::default());
+
+    let i2c = I2c::new(
+        p.I2C1,
+        p.PA3,
+        p.PA2,
+        Irqs,
+        p.DMA1_CH2,
+        p.DMA1_CH1,
+        Hertz(100_000),
+        Default::default(),
+    )
+    .unwrap();
+    let mut i2c = i2c.into_slave_multimaster(SlaveAddrConfig::basic(ADDRESS));
+
+    let mut regs = [0u8; 16];
+    let mut reg = 0usize;
+    loop {
+        let cmd = match i2c.listen().await {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                error!("listen error: {:?}", e);
+                continue;
+            }
+        };
+
+        match cmd.kind {
+            SlaveCommandKind::Write => {
+                let mut buf = [0u8; 17];
+                match i2c.respond_to_write(&mut buf).await {
+                    Ok(0) => {}
+                    Ok(n) => {
+                        reg = buf[0] as usize % regs.len();
+                        for (i, b) in buf[1..n].iter().enumerate() {
+                            regs[(reg + i) % regs.len()] = *b;
+                        }
+                    }
+                    Err(e) => error!("write error: {:?}", e),
+                }
+            }
+            SlaveCommandKind::Read => {
+                if let Err(e) = i2c.respond_to_read(&regs[reg..]).await {
+                    error!("read error: {:?}", e);
+                }
+            }
+        }
+    }
+}
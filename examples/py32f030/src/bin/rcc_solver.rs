@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::gpio::{Level, Output, Speed};
+use py32_hal::rcc::{self, ClockSource, ClockTree, HsiFs};
+use py32_hal::time::mhz;
+use {defmt_rtt as _, panic_halt as _};
+
+// Solved at compile time: HSI 24MHz -> PLL x2 -> 48MHz SYSCLK, PCLK = HCLK / 2.
+// Asking for e.g. 50MHz here fails the build instead of hanging at startup.
+const RCC: rcc::Config = match ClockTree::new(ClockSource::HSI(HsiFs::HSI_24MHZ), mhz(48))
+    .pclk(mhz(24))
+    .build()
+{
+    Ok(config) => config,
+    Err(e) => panic!("{}", e.as_str()),
+};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut cfg: py32_hal::Config = Default::default();
+    cfg.rcc = RCC;
+    let p = py32_hal::init(cfg);
+
+    info!("Hello World!");
+
+    let mut led = Output::new(p.PB1, Level::High, Speed::Low);
+
+    loop {
+        info!("high");
+        led.set_high();
+        cortex_m::asm::delay(9_600_000);
+
+        info!("low");
+        led.set_low();
+        cortex_m::asm::delay(9_600_000);
+    }
+}
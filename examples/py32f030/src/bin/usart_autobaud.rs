@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::usart::{AutoBaudMode, Config, Uart};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let mut config = Config::default();
+    config.auto_baud = Some(AutoBaudMode::Frame7F);
+    let mut usart =
+        Uart::new(p.USART1, p.PA3, p.PA2, Irqs, p.DMA1_CH3, p.DMA1_CH1, config).unwrap();
+
+    // The host sends 0x7F at whatever baud rate it likes.
+    let baudrate = unwrap!(usart.detect_baudrate().await);
+    info!("detected {} baud", baudrate);
+
+    unwrap!(usart.write(b"Hello Embassy World!\r\n").await);
+
+    let mut buf = [0u8; 32];
+    loop {
+        let n = unwrap!(usart.read_until_idle(&mut buf).await);
+        unwrap!(usart.write(&buf[..n]).await);
+    }
+}
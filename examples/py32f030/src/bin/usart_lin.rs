@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use py32_hal::usart::{ChecksumModel, Config, Lin, Uart};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    // PA2/PA3 go to a LIN transceiver (e.g. TJA1021).
+    let mut config = Config::default();
+    config.baudrate = 19200;
+    let uart = Uart::new(p.USART1, p.PA3, p.PA2, Irqs, p.DMA1_CH3, p.DMA1_CH1, config).unwrap();
+    let mut lin = Lin::new(uart, config).unwrap();
+
+    let mut counter = 0u8;
+    loop {
+        // Publish frame 0x10 as the master.
+        unwrap!(lin.send_header(0x10).await);
+        unwrap!(lin.respond(0x10, &[counter, 0xA5], ChecksumModel::Enhanced).await);
+
+        // Request frame 0x20 from a slave.
+        let mut buf = [0u8; 4];
+        unwrap!(lin.send_header(0x20).await);
+        match lin.read_frame(0x20, &mut buf, ChecksumModel::Enhanced).await {
+            Ok(()) => info!("0x20: {:x}", buf),
+            Err(e) => warn!("0x20: {}", e),
+        }
+
+        counter = counter.wrapping_add(1);
+        Timer::after_millis(100).await;
+    }
+}
@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::gpio::{Level, Output, Speed};
+use py32_hal::usart::modbus::{Exception, Handler, Slave};
+use py32_hal::usart::{Config, Parity, Uart};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+const DMA_BUF_SIZE: usize = 256;
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+/// Coil 0 drives the LED, holding registers 0..4 are plain storage.
+struct Device {
+    led: Output<'static>,
+    registers: [u16; 4],
+}
+
+impl Handler for Device {
+    fn read_coil(&mut self, address: u16) -> Result<bool, Exception> {
+        match address {
+            0 => Ok(self.led.is_set_high()),
+            _ => Err(Exception::IllegalDataAddress),
+        }
+    }
+
+    fn write_coil(&mut self, address: u16, value: bool) -> Result<(), Exception> {
+        match address {
+            0 => self.led.set_level(value.into()),
+            _ => return Err(Exception::IllegalDataAddress),
+        }
+        Ok(())
+    }
+
+    fn read_holding_register(&mut self, address: u16) -> Result<u16, Exception> {
+        self.registers
+            .get(address as usize)
+            .copied()
+            .ok_or(Exception::IllegalDataAddress)
+    }
+
+    fn write_holding_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+        let register = self
+            .registers
+            .get_mut(address as usize)
+            .ok_or(Exception::IllegalDataAddress)?;
+        *register = value;
+        Ok(())
+    }
+}
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    // 19200 baud, 8E1. PA12, the USART1 RTS/DE pin, drives DE of the RS-485 transceiver.
+    let mut config = Config::default();
+    config.baudrate = 19200;
+    config.parity = Parity::ParityEven;
+    let usart = Uart::new_with_de(
+        p.USART1, p.PA3, p.PA2, Irqs, p.PA12, p.DMA1_CH3, p.DMA1_CH1, config,
+    )
+    .unwrap();
+
+    let (tx, rx) = usart.split();
+    static mut DMA_BUF: [u8; DMA_BUF_SIZE] = [0; DMA_BUF_SIZE];
+    let rx = rx.into_ring_buffered(unsafe { &mut *core::ptr::addr_of_mut!(DMA_BUF) });
+
+    let mut device = Device {
+        led: Output::new(p.PB1, Level::Low, Speed::Low),
+        registers: [0; 4],
+    };
+    let mut slave = Slave::new(rx, tx, 1).unwrap();
+    slave.run(&mut device).await
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::usart::{Config, DataBits, Uart, WakeupMethod};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+/// Address of this node on the multi-drop bus.
+const OWN_ADDRESS: u8 = 0x5;
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    // 9 data bits: bit 8 marks address characters.
+    let mut config = Config::default();
+    config.data_bits = DataBits::DataBits9;
+    config.wakeup_method = WakeupMethod::AddressMark;
+    config.address = OWN_ADDRESS;
+    let mut usart =
+        Uart::new(p.USART1, p.PA3, p.PA2, Irqs, p.DMA1_CH3, p.DMA1_CH1, config).unwrap();
+
+    let mut buf = [0u16; 16];
+    loop {
+        // Ignore all traffic until a master addresses this node.
+        usart.enter_mute_mode();
+        let n = unwrap!(usart.read_until_idle_u16(&mut buf).await);
+        let Some((&address, data)) = buf[..n].split_first() else {
+            continue;
+        };
+        info!("address {:x}: {:x}", address & 0xFF, data);
+
+        // Reply to the master (address 0).
+        unwrap!(usart.write_u16(&[0x100]).await);
+        unwrap!(usart.write_u16(data).await);
+    }
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+use core::fmt::Write as _;
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_time::{Instant, Timer};
+use heapless::String;
+use py32_hal::bind_interrupts;
+use py32_hal::peripherals;
+use py32_hal::usart::{self, Config, Uart};
+use {defmt_rtt as _, panic_probe as _};
+
+const TX_BUF_SIZE: usize = 256;
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let mut config = Config::default();
+    config.baudrate = 921_600;
+    // The ring-buffered transmitter needs the USART interrupt, which `Uart::new` binds.
+    let usart = Uart::new(p.USART1, p.PA3, p.PA2, Irqs, p.DMA1_CH3, p.DMA1_CH1, config).unwrap();
+    let (tx, _rx) = usart.split();
+
+    static mut TX_BUF: [u8; TX_BUF_SIZE] = [0; TX_BUF_SIZE];
+    let mut tx = tx.into_ring_buffered(unsafe { &mut *core::ptr::addr_of_mut!(TX_BUF) });
+
+    let mut seq: u32 = 0;
+    loop {
+        let mut line: String<64> = String::new();
+        write!(line, "{} t={}us\r\n", seq, Instant::now().as_micros()).unwrap();
+
+        // Returns as soon as the line is copied, the DMA sends it in the background.
+        let n = tx.write(line.as_bytes());
+        if n < line.len() {
+            // Ring buffer full: wait for space instead of dropping the rest.
+            tx.write_all(&line.as_bytes()[n..]).await.unwrap();
+        }
+
+        seq = seq.wrapping_add(1);
+        if seq % 1000 == 0 {
+            tx.flush().await.unwrap();
+            info!("{} lines sent", seq);
+        }
+        Timer::after_micros(200).await;
+    }
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use py32_hal::gpio::{Level, Output, Speed};
+use py32_hal::time::khz;
+use py32_hal::usart::{SpiConfig, UsartSpi};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = py32_hal::init(Default::default());
+    info!("Hello World!");
+
+    let mut config = SpiConfig::default();
+    config.frequency = khz(500);
+
+    // CK = SCK, TX = MOSI, RX = MISO
+    let mut spi = UsartSpi::new(
+        p.USART1, Irqs, p.PA8, p.PA2, p.PA3, p.DMA1_CH3, p.DMA1_CH1, config,
+    )
+    .unwrap();
+    let mut cs = Output::new(p.PA4, Level::High, Speed::Low);
+
+    loop {
+        // The USART shifts LSB first, reverse the bits for an MSB-first device.
+        let mut buf = [0x9Fu8.reverse_bits(), 0, 0, 0];
+        cs.set_low();
+        unwrap!(spi.transfer_in_place(&mut buf).await);
+        cs.set_high();
+
+        let id = buf.map(u8::reverse_bits);
+        info!("JEDEC id: {:02x}", id[1..]);
+
+        Timer::after_millis(1000).await;
+    }
+}
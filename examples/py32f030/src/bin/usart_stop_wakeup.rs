@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_time::{with_timeout, Duration};
+use embedded_io_async::Read;
+use embedded_io_async::Write;
+use py32_hal::rcc::HsiFs;
+use py32_hal::usart::{BufferedUart, Config};
+use py32_hal::{bind_interrupts, peripherals, usart};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    USART1 => usart::BufferedInterruptHandler<peripherals::USART1>;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut cfg: py32_hal::Config = Default::default();
+    cfg.rcc.hsi = Some(HsiFs::HSI_24MHZ);
+    let mut p = py32_hal::init(cfg);
+    info!("Hello World!");
+
+    let mut config = Config::default();
+    config.baudrate = 9600;
+    let mut tx_buf = [0u8; 64];
+    let mut rx_buf = [0u8; 64];
+    let mut usart = BufferedUart::new(
+        p.USART1,
+        Irqs,
+        p.PA3,
+        p.PA2,
+        &mut tx_buf,
+        &mut rx_buf,
+        config,
+    )
+    .unwrap();
+
+    loop {
+        // Sleep until the modem talks to us. PA3 (RX) is on EXTI line 3.
+        usart.flush().await.unwrap();
+        info!("entering Stop mode");
+        usart.enter_stop_until_rx(p.EXTI3.reborrow());
+        info!("woken up by RX");
+
+        // Echo until the line has been quiet for a second. The character that woke us is lost
+        // or garbled, so the modem sends a preamble byte first.
+        let mut buf = [0u8; 64];
+        while let Ok(n) = with_timeout(Duration::from_secs(1), usart.read(&mut buf)).await {
+            match n {
+                Ok(n) => usart.write_all(&buf[..n]).await.unwrap(),
+                Err(e) => warn!("read error after wake-up: {}", e),
+            }
+        }
+    }
+}
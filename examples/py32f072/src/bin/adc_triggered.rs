@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+// py32f072 timer-triggered ADC example
+// TIM3 emits an update event on TRGO at 10 kHz, and each event converts the sequence once.
+// Unlike free-running continuous mode, the sample rate is fixed by the timer.
+
+use cortex_m::singleton;
+use defmt::*;
+use embassy_executor::Spawner;
+use py32_hal::adc::{Adc, SampleTime, Sequence};
+use py32_hal::time::Hertz;
+use py32_hal::timer::low_level::Timer;
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut p = py32_hal::init(Default::default());
+
+    const ADC_BUF_SIZE: usize = 200;
+    let adc_data: &mut [u16; ADC_BUF_SIZE] =
+        singleton!(ADCDAT : [u16; ADC_BUF_SIZE] = [0u16; ADC_BUF_SIZE]).unwrap();
+
+    let timer = Timer::new(p.TIM3);
+    let adc = Adc::new(p.ADC1);
+    let mut adc = adc.start_triggered(&timer, Hertz::khz(10), p.DMA1_CH1, adc_data);
+
+    adc.set_sample_sequence(Sequence::One, &mut p.PA0, SampleTime::CYCLES13_5);
+    adc.set_sample_sequence(Sequence::Two, &mut p.PA1, SampleTime::CYCLES13_5);
+
+    let mut buffer = [0u16; ADC_BUF_SIZE / 2];
+    loop {
+        match adc.read(&mut buffer).await {
+            Ok(_) => {
+                let pa0 = buffer.iter().step_by(2).map(|&v| v as u32).sum::<u32>();
+                let pa1 = buffer
+                    .iter()
+                    .skip(1)
+                    .step_by(2)
+                    .map(|&v| v as u32)
+                    .sum::<u32>();
+                let n = buffer.len() as u32 / 2;
+                info!("PA0: {}, PA1: {}", pa0 / n, pa1 / n);
+            }
+            Err(e) => {
+                warn!("Error: {:?}", e);
+                buffer = [0u16; ADC_BUF_SIZE / 2];
+                let _ = adc.start();
+            }
+        }
+    }
+}
@@ -15,7 +15,8 @@ async fn main(_spawner: Spawner) {
     info!("Hello world!");
     let p = py32_hal::init(Default::default());
 
-    let mut i2c = I2c::new_blocking(p.I2C1, p.PA9, p.PA10, Hertz(100_000), Default::default());
+    let mut i2c =
+        I2c::new_blocking(p.I2C1, p.PA9, p.PA10, Hertz(100_000), Default::default()).unwrap();
 
     let mut data = [0u8; 1];
 
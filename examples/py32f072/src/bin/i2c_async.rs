@@ -28,7 +28,8 @@ async fn main(_spawner: Spawner) {
         p.DMA1_CH1,
         Hertz(100_000),
         Default::default(),
-    );
+    )
+    .unwrap();
 
     loop {
         let write_data = [0xC2, 0x11];
@@ -16,6 +16,11 @@ mod _version;
 pub use _version::*;
 
 use core::marker::PhantomData;
+#[cfg(any(adc_v1, adc_v1b))]
+use core::sync::atomic::AtomicBool;
+
+#[cfg(any(adc_v1, adc_v1b))]
+use embassy_hal_internal::atomic_ring_buffer::RingBuffer;
 use embassy_sync::waitqueue::AtomicWaker;
 
 pub use crate::pac::adc::vals;
@@ -35,16 +40,75 @@ pub struct Adc<'d, T: Instance> {
 
 pub struct State {
     pub waker: AtomicWaker,
+    pub watchdog_waker: AtomicWaker,
+    /// Samples of a `RingBufferedAdc`, filled from the interrupt
+    #[cfg(any(adc_v1, adc_v1b))]
+    pub ring: RingBuffer,
+    #[cfg(any(adc_v1, adc_v1b))]
+    pub ring_overrun: AtomicBool,
 }
 
 impl State {
     pub const fn new() -> Self {
         Self {
             waker: AtomicWaker::new(),
+            watchdog_waker: AtomicWaker::new(),
+            #[cfg(any(adc_v1, adc_v1b))]
+            ring: RingBuffer::new(),
+            #[cfg(any(adc_v1, adc_v1b))]
+            ring_overrun: AtomicBool::new(false),
         }
     }
 }
 
+/// Channels monitored by the analog watchdog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WatchdogChannels {
+    /// Every converted channel.
+    All,
+    /// A single channel, by channel number.
+    Single(u8),
+}
+
+impl WatchdogChannels {
+    /// Monitor only `channel`.
+    pub fn from_channel<T>(channel: &impl AdcChannel<T>) -> Self {
+        Self::Single(channel.channel())
+    }
+}
+
+/// Analog watchdog of a streaming `RingBufferedAdc`, returned by its `set_watchdog`.
+///
+/// It can be awaited alongside `RingBufferedAdc::read`, e.g. from another task.
+///
+/// The handle does not borrow the `RingBufferedAdc`, which is what allows this. As a
+/// consequence:
+/// - All handles of an ADC share one waker, so only one task may wait at a time: a second
+///   waiter takes the wake-up from the first. Keep a single handle; calling `set_watchdog`
+///   again only changes the thresholds.
+/// - Dropping the `RingBufferedAdc` turns the watchdog off, a wait still pending then never
+///   completes. While the conversions are stopped, e.g. after an overrun, it waits for them
+///   to be restarted.
+pub struct Watchdog<'d, T: Instance> {
+    _phantom: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Watchdog<'d, T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Wait until a monitored conversion is outside the watchdog window.
+    ///
+    /// Needs the ADC interrupt to be bound.
+    pub async fn wait_for_out_of_window(&mut self) {
+        _version::wait_for_out_of_window::<T>().await
+    }
+}
+
 trait SealedInstance {
     #[allow(unused)]
     fn regs() -> crate::pac::adc::Adc;
@@ -146,9 +210,128 @@ macro_rules! impl_adc_pin {
                 $ch
             }
         }
+        impl embedded_hal_02::adc::Channel<peripherals::$inst>
+            for crate::Peri<'_, crate::peripherals::$pin>
+        {
+            type ID = u8;
+
+            fn channel() -> u8 {
+                $ch
+            }
+        }
     };
 }
 
+impl<'d, T, P> embedded_hal_02::adc::OneShot<T, u16, P> for Adc<'d, T>
+where
+    T: Instance,
+    P: AdcChannel<T> + embedded_hal_02::adc::Channel<T>,
+{
+    type Error = core::convert::Infallible;
+
+    fn read(&mut self, pin: &mut P) -> nb::Result<u16, Self::Error> {
+        Ok(self.blocking_read(pin))
+    }
+}
+
+/// A channel bound to the ADC converting it, for drivers measuring a single input, such as
+/// battery gauges or NTC thermistors.
+pub struct Voltmeter<'a, 'd, T: Instance> {
+    adc: &'a mut Adc<'d, T>,
+    channel: &'a mut AnyAdcChannel<T>,
+}
+
+impl<'a, 'd, T: Instance> Voltmeter<'a, 'd, T> {
+    /// Bind `channel` to `adc` until the voltmeter is dropped.
+    pub fn new(adc: &'a mut Adc<'d, T>, channel: &'a mut AnyAdcChannel<T>) -> Self {
+        Self { adc, channel }
+    }
+
+    /// Convert the channel and return the raw reading.
+    pub async fn read(&mut self) -> u16 {
+        self.adc.read(&mut *self.channel).await
+    }
+}
+
+impl<'a, 'd> Voltmeter<'a, 'd, peripherals::ADC1> {
+    /// Convert the channel and return its voltage in millivolts, see [`Adc::read_mv`].
+    pub async fn read_mv(&mut self) -> u16 {
+        self.adc.read_mv(&mut *self.channel).await
+    }
+}
+
+/// Temperatures of the two factory calibration points of the temperature sensor, in °C.
+const TS_CAL_TEMPS: (i32, i32) = (30, 85);
+
+impl<'d> Adc<'d, peripherals::ADC1> {
+    fn resolution(&self) -> Resolution {
+        cfg_if::cfg_if! {
+            if #[cfg(adc_v2)] {
+                peripherals::ADC1::regs().cr1().read().res()
+            } else {
+                peripherals::ADC1::regs().cfgr1().read().res()
+            }
+        }
+    }
+
+    /// Convert an internal channel, which needs the longest sample time.
+    async fn read_internal(&mut self, channel: &mut impl AdcChannel<peripherals::ADC1>) -> u16 {
+        let sample_time = self.sample_time;
+        self.sample_time = SampleTime::CYCLES239_5;
+        let sample = self.read(channel).await;
+        self.sample_time = sample_time;
+
+        sample
+    }
+
+    /// Measure the analog supply VDDA against the internal voltage reference, in millivolts.
+    ///
+    /// VREFINT has no factory calibration word, so the result is as accurate as the nominal
+    /// [`VREF_INT`], see the embedded reference voltage spread in the datasheet.
+    pub async fn read_vdda_mv(&mut self) -> u16 {
+        #[cfg(adc_v2)]
+        let mut vref = self.enable_vrefint();
+        #[cfg(not(adc_v2))]
+        let mut vref = self.enable_vref();
+
+        let max = resolution_to_max_count(self.resolution());
+        let sample = self.read_internal(&mut vref).await;
+
+        (VREF_INT * max / u32::from(sample).max(1)) as u16
+    }
+
+    /// Convert `channel` and return its voltage in millivolts, compensated for the measured
+    /// VDDA.
+    pub async fn read_mv(&mut self, channel: &mut impl AdcChannel<peripherals::ADC1>) -> u16 {
+        let vdda = u32::from(self.read_vdda_mv().await);
+        let max = resolution_to_max_count(self.resolution());
+        let sample = self.read(channel).await;
+
+        (u32::from(sample) * vdda / max) as u16
+    }
+
+    /// Measure the chip temperature in °C, using the factory calibration of the temperature
+    /// sensor in the config bytes.
+    pub async fn read_temperature_celsius(&mut self) -> f32 {
+        let vdda = u32::from(self.read_vdda_mv().await);
+        let mut temperature = self.enable_temperature();
+        let max = resolution_to_max_count(self.resolution());
+        let sample = self.read_internal(&mut temperature).await;
+
+        // Scale to a 12-bit reading at the calibration VDDA.
+        #[cfg(adc_v2)]
+        let calib_mv = _version::VREF_CALIB_MV;
+        #[cfg(not(adc_v2))]
+        let calib_mv = _version::VDDA_CALIB_MV;
+        let sample = f32::from(sample) * 4095.0 * vdda as f32 / (max * calib_mv) as f32;
+        let (cal1, cal2) = _version::ts_cal();
+        let (cal1, cal2) = (f32::from(cal1), f32::from(cal2));
+        let (temp1, temp2) = TS_CAL_TEMPS;
+
+        temp1 as f32 + (sample - cal1) * (temp2 - temp1) as f32 / (cal2 - cal1)
+    }
+}
+
 /// Get the maximum reading value for this resolution.
 ///
 /// This is `2**n - 1`.
@@ -0,0 +1,318 @@
+//! Continuous ADC sampling into a software ring buffer
+//!
+//! Without DMA (e.g. on the PY32F002B), each end of conversion raises an interrupt, and the
+//! interrupt handler moves the sample from the data register into the ring. The API matches
+//! the DMA based `RingBufferedAdc` of the other ADC version.
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::mem;
+use core::slice;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::Poll;
+
+use py32_metapac::adc::vals::Exten;
+
+use crate::adc::{Adc, AdcChannel, Instance, SampleTime, Watchdog, WatchdogChannels};
+use crate::rcc;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OverrunError;
+
+/// Store a finished conversion, or stop the ADC if the ring is full.
+pub(super) fn on_interrupt<T: Instance>() {
+    let r = T::regs();
+    let state = T::state();
+    let isr = r.isr().read();
+
+    let stored = if isr.ovr() {
+        false
+    } else if isr.eoc() {
+        let data = r.dr().read().data();
+        let mut writer = unsafe { state.ring.writer() };
+        let buf = writer.push_slice();
+        // Samples are stored as byte pairs; the ring length is even, so a free slice is
+        // always a whole number of samples.
+        if buf.len() >= 2 {
+            buf[..2].copy_from_slice(&data.to_ne_bytes());
+            writer.push_done(2);
+            true
+        } else {
+            false
+        }
+    } else {
+        return;
+    };
+
+    if !stored {
+        r.cr().modify(|reg| reg.set_adstp(true));
+        r.ier().modify(|w| {
+            w.set_eocie(false);
+            w.set_ovrie(false);
+        });
+        state.ring_overrun.store(true, Ordering::Relaxed);
+    }
+
+    state.waker.wake();
+}
+
+/// Continuously converts the enabled channels into a software ring buffer.
+pub struct RingBufferedAdc<'d, T: Instance> {
+    _phantom: PhantomData<&'d mut [u16]>,
+    buf: *mut u16,
+    len: usize,
+    sample_time: SampleTime,
+}
+
+impl<'d, T: Instance> Adc<'d, T> {
+    /// Configures the ADC for continuous data acquisition into the ring buffer `buf`.
+    ///
+    /// The length of `buf` should be a multiple of the ADC channel count, and large enough
+    /// to hold the samples converted between two calls to `read`.
+    ///
+    /// `read` method is used to read out measurements from the ring buffer, and its buffer
+    /// should be exactly half of the `buf` length.
+    ///
+    /// [`read`]: RingBufferedAdc::read
+    pub fn into_ring_buffered(self, buf: &'d mut [u16]) -> RingBufferedAdc<'d, T> {
+        assert!(!buf.is_empty());
+
+        let sample_time = self.sample_time;
+        T::regs().chselr().write(|_| {});
+
+        // Don't disable the clock
+        mem::forget(self);
+
+        RingBufferedAdc {
+            _phantom: PhantomData,
+            buf: buf.as_mut_ptr(),
+            len: buf.len(),
+            sample_time,
+        }
+    }
+}
+
+impl<'d, T: Instance> RingBufferedAdc<'d, T> {
+    fn is_on() -> bool {
+        T::regs().cr().read().adstart()
+    }
+
+    /// Add `channel` to the sampled channels.
+    ///
+    /// Channels are converted in ascending channel number, whatever the order they are
+    /// enabled in. This stops sampling; it resumes with the next `start` or `read`.
+    pub fn enable_channel(&mut self, channel: &mut impl AdcChannel<T>) {
+        self.teardown_adc();
+
+        channel.setup();
+        T::regs()
+            .chselr()
+            .modify(|reg| reg.set_chselx(channel.channel() as usize, true));
+    }
+
+    /// Set the sample time of all channels. This stops sampling, like `enable_channel`.
+    pub fn set_sample_time(&mut self, sample_time: SampleTime) {
+        self.teardown_adc();
+
+        self.sample_time = sample_time;
+    }
+
+    /// Configure the analog watchdog to monitor `channels`, with `low` and `high` thresholds
+    /// in 12-bit counts, and return a handle to wait on it.
+    ///
+    /// The thresholds are 12-bit counts at every resolution, see [`Adc::set_watchdog`]. Only
+    /// one task may wait on the watchdog at a time, see [`Watchdog`].
+    pub fn set_watchdog(
+        &mut self,
+        channels: WatchdogChannels,
+        low: u16,
+        high: u16,
+    ) -> Watchdog<'d, T> {
+        super::set_watchdog::<T>(channels, low, high);
+
+        Watchdog::new()
+    }
+
+    /// Turn the analog watchdog off.
+    pub fn disable_watchdog(&mut self) {
+        super::disable_watchdog::<T>();
+    }
+
+    /// Clears the ring buffer and starts continuous conversions.
+    pub fn start(&mut self) -> Result<(), OverrunError> {
+        self.teardown_adc();
+        self.setup_adc();
+
+        Ok(())
+    }
+
+    fn stop(&mut self, err: OverrunError) -> Result<usize, OverrunError> {
+        self.teardown_adc();
+        Err(err)
+    }
+
+    /// Stops conversions.
+    /// Calling `start` restarts them.
+    ///
+    /// [`start`]: #method.start
+    pub fn teardown_adc(&mut self) {
+        let r = T::regs();
+
+        if Self::is_on() {
+            r.cr().modify(|reg| reg.set_adstp(true));
+            while r.cr().read().adstp() {}
+        }
+
+        r.ier().modify(|w| {
+            w.set_eocie(false);
+            w.set_ovrie(false);
+        });
+        r.cfgr1().modify(|reg| reg.set_cont(false));
+
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn setup_adc(&mut self) {
+        let r = T::regs();
+        let state = T::state();
+
+        // Conversions are stopped, so the interrupt does not touch the ring.
+        unsafe {
+            state.ring.deinit();
+            state.ring.init(self.buf as *mut u8, self.len * 2);
+        }
+        state.ring_overrun.store(false, Ordering::Relaxed);
+
+        compiler_fence(Ordering::SeqCst);
+
+        r.isr().write(|reg| {
+            reg.set_eoc(true);
+            reg.set_eosmp(true);
+            reg.set_ovr(true);
+        });
+        r.smpr().modify(|reg| reg.set_smp(self.sample_time.into()));
+        r.cfgr1().modify(|reg| {
+            reg.set_dmaen(false);
+            // Convert continuously, unless each sequence is started by an external trigger
+            reg.set_cont(reg.exten() == Exten::DISABLED);
+        });
+        r.ier().modify(|w| {
+            w.set_eocie(true);
+            w.set_ovrie(true);
+        });
+
+        // See `Adc::convert`: ADEN is set again before every ADSTART.
+        r.cr().modify(|reg| reg.set_aden(true));
+        super::blocking_delay_us(1);
+        r.cr().modify(|reg| reg.set_adstart(true));
+    }
+
+    /// Move samples from the ring into `buf`, returning how many were moved.
+    fn pop(buf: &mut [u16]) -> usize {
+        let mut reader = unsafe { T::state().ring.reader() };
+        let mut n = 0;
+
+        while n < buf.len() {
+            let (p, len) = reader.pop_buf();
+            let len = (len / 2).min(buf.len() - n);
+            if len == 0 {
+                break;
+            }
+
+            // Samples start at even offsets of the `u16` buffer, so they are aligned.
+            let samples = unsafe { slice::from_raw_parts(p as *const u16, len) };
+            buf[n..n + len].copy_from_slice(samples);
+            reader.pop_done(len * 2);
+            n += len;
+        }
+
+        n
+    }
+
+    /// Read samples that are readily available in the ring buffer.
+    /// If no samples are currently available the call waits until some are
+    /// (at least one and at most the length of `buf`).
+    ///
+    /// Conversions are started if `start()` has not been previously called.
+    ///
+    /// Conversions are stopped if an error is returned.
+    /// They must then manually be started again by calling `start()` or by re-calling `read()`.
+    pub fn blocking_read<const N: usize>(
+        &mut self,
+        buf: &mut [u16; N],
+    ) -> Result<usize, OverrunError> {
+        if !Self::is_on() {
+            self.start()?;
+        }
+
+        loop {
+            if T::state().ring_overrun.load(Ordering::Relaxed) {
+                return self.stop(OverrunError);
+            }
+
+            let len = Self::pop(buf);
+            if len != 0 {
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Reads measurements from the ring buffer.
+    ///
+    /// This method fills the provided `measurements` array with ADC readings. The length of
+    /// the `measurements` array should be exactly half of the ring buffer length.
+    ///
+    /// The measurements of each sequence are in ascending channel number, e.g. for enabled
+    /// channels 0, 1 and 4 `measurements` contain `[ch0 ch1 ch4 ch0 ch1 ch4 ..]`.
+    ///
+    /// If an error is returned, the ring buffer overran and the process must be restarted by
+    /// calling `start` or `read` again.
+    ///
+    /// [`start`]: #method.start
+    pub async fn read<const N: usize>(
+        &mut self,
+        measurements: &mut [u16; N],
+    ) -> Result<usize, OverrunError> {
+        assert_eq!(
+            self.len / 2,
+            N,
+            "Buffer size must be half the size of the ring buffer"
+        );
+
+        if !Self::is_on() {
+            self.start()?;
+        }
+
+        let mut filled = 0;
+        let overrun = poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::state().ring_overrun.load(Ordering::Relaxed) {
+                return Poll::Ready(true);
+            }
+
+            filled += Self::pop(&mut measurements[filled..]);
+            if filled == N {
+                Poll::Ready(false)
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        if overrun {
+            self.stop(OverrunError)
+        } else {
+            Ok(N)
+        }
+    }
+}
+
+impl<T: Instance> Drop for RingBufferedAdc<'_, T> {
+    fn drop(&mut self) {
+        self.teardown_adc();
+        super::disable_watchdog::<T>();
+        unsafe { T::state().ring.deinit() };
+        rcc::disable::<T>();
+    }
+}
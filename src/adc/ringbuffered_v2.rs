@@ -7,15 +7,32 @@ use core::mem;
 use core::sync::atomic::{compiler_fence, Ordering};
 
 use embassy_hal_internal::Peri;
-use py32_metapac::adc::vals::SampleTime;
+use py32_metapac::adc::vals::{Extsel, SampleTime};
 
-use crate::adc::{Adc, AdcChannel, Instance, RxDma};
+use crate::adc::{Adc, AdcChannel, Instance, RxDma, Watchdog, WatchdogChannels};
 use crate::dma::{Priority, ReadableRingBuffer, TransferOptions};
 use crate::rcc;
+use crate::time::Hertz;
+use crate::timer::low_level::{MasterMode, Timer};
+use crate::timer::BasicInstance;
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct OverrunError;
 
+trait SealedTriggerTimer {
+    /// External trigger selecting the TRGO of this timer
+    const TRGO: Extsel;
+}
+
+/// Timer whose TRGO can start conversions, see [`Adc::start_triggered`].
+#[allow(private_bounds)]
+pub trait TriggerTimer: SealedTriggerTimer + BasicInstance {}
+
+impl SealedTriggerTimer for crate::peripherals::TIM3 {
+    const TRGO: Extsel = Extsel::TIM3_TRGO;
+}
+impl TriggerTimer for crate::peripherals::TIM3 {}
+
 fn clear_interrupt_flags(r: crate::pac::adc::Adc) {
     r.sr().modify(|regs| {
         regs.set_eoc(false);
@@ -132,6 +149,30 @@ impl<'d, T: Instance> Adc<'d, T> {
             ring_buf,
         }
     }
+
+    /// Configures the ADC to convert the sequence once per update event of `timer`, at `freq`,
+    /// and returns a [`RingBufferedAdc`] collecting the results.
+    ///
+    /// The ADC is triggered by the TRGO of `timer`, which is started here; conversions begin
+    /// with the first `read` or `start`. `timer` has to be kept alive while sampling.
+    ///
+    /// See [`into_ring_buffered`](Self::into_ring_buffered) for sizing `dma_buf`.
+    pub fn start_triggered<TIM: TriggerTimer>(
+        mut self,
+        timer: &Timer<'_, TIM>,
+        freq: Hertz,
+        dma: Peri<'d, impl RxDma<T>>,
+        dma_buf: &'d mut [u16],
+    ) -> RingBufferedAdc<'d, T> {
+        self.set_external_trigger(TIM::TRGO);
+
+        timer.stop();
+        timer.set_master_mode(MasterMode::UPDATE);
+        timer.set_frequency(freq);
+        timer.start();
+
+        self.into_ring_buffered(dma, dma_buf)
+    }
 }
 
 impl<'d, T: Instance> RingBufferedAdc<'d, T> {
@@ -235,6 +276,27 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
         Self::start_adc();
     }
 
+    /// Configure the analog watchdog to monitor `channels` of the sequence, with `low` and
+    /// `high` thresholds in 12-bit counts, and return a handle to wait on it.
+    ///
+    /// The thresholds are 12-bit counts at every resolution, see [`Adc::set_watchdog`]. Only
+    /// one task may wait on the watchdog at a time, see [`Watchdog`].
+    pub fn set_watchdog(
+        &mut self,
+        channels: WatchdogChannels,
+        low: u16,
+        high: u16,
+    ) -> Watchdog<'d, T> {
+        super::set_watchdog::<T>(channels, low, high);
+
+        Watchdog::new()
+    }
+
+    /// Turn the analog watchdog off.
+    pub fn disable_watchdog(&mut self) {
+        super::disable_watchdog::<T>();
+    }
+
     /// Turns on ADC if it is not already turned on and starts continuous DMA transfer.
     pub fn start(&mut self) -> Result<(), OverrunError> {
         self.setup_adc();
@@ -316,8 +378,8 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
         r.cr2().modify(|w| {
             // Enable DMA mode
             w.set_dma(true);
-            // Enable continuous conversions
-            w.set_cont(true);
+            // Convert continuously, unless each sequence is started by an external trigger
+            w.set_cont(w.extsel() == Extsel::SWSTART);
         });
 
         // Begin ADC conversions
@@ -445,6 +507,7 @@ impl<'d, T: Instance> RingBufferedAdc<'d, T> {
 impl<T: Instance> Drop for RingBufferedAdc<'_, T> {
     fn drop(&mut self) {
         self.teardown_adc();
+        super::disable_watchdog::<T>();
         rcc::disable::<T>();
     }
 }
@@ -6,18 +6,53 @@ use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::Peri;
-use py32_metapac::adc::vals::Ckmode;
+#[cfg(dma)]
+use py32_metapac::adc::vals::Dmacfg;
+use py32_metapac::adc::vals::{Ckmode, Exten, Extsel};
 
 use super::blocking_delay_us;
-use crate::adc::{Adc, AdcChannel, Instance, Resolution, SampleTime};
+use crate::adc::{Adc, AdcChannel, Instance, Resolution, SampleTime, WatchdogChannels};
+#[cfg(dma)]
+use crate::adc::{AnyAdcChannel, RxDma, SealedAdcChannel};
+#[cfg(dma)]
+use crate::dma::Transfer;
 use crate::interrupt::typelevel::Interrupt;
 use crate::peripherals::ADC1;
 use crate::{interrupt, rcc};
 
+mod ringbuffered_v1;
+pub use ringbuffered_v1::{OverrunError, RingBufferedAdc};
+
 pub const VDDA_CALIB_MV: u32 = 3300;
+/// Internal voltage reference, in millivolts.
+///
+/// This is the nominal value: the factory calibration in the system memory only covers the
+/// temperature sensor, there is no VREFINT calibration word on these chips.
 pub const VREF_INT: u32 = 1200;
 
+/// Factory calibration of the temperature sensor: 12-bit readings at 30 °C and 85 °C.
+///
+/// py32-metapac does not describe the system memory, so the addresses are taken from the
+/// temperature sensor section of the ADC chapter of the reference manuals (TS_CAL1 and
+/// TS_CAL2 in the "temperature sensor calibration values" table).
+pub(super) fn ts_cal() -> (u16, u16) {
+    cfg_if::cfg_if! {
+        if #[cfg(py32f002b)] {
+            // PY32F002B reference manual
+            const TS_CAL1: *const u16 = 0x1FFF_011C as *const u16;
+            const TS_CAL2: *const u16 = 0x1FFF_0120 as *const u16;
+        } else {
+            // PY32F030/PY32F003 reference manual
+            const TS_CAL1: *const u16 = 0x1FFF_0F14 as *const u16;
+            const TS_CAL2: *const u16 = 0x1FFF_0F18 as *const u16;
+        }
+    }
+
+    unsafe { (TS_CAL1.read_volatile(), TS_CAL2.read_volatile()) }
+}
+
 /// Interrupt handler.
 pub struct InterruptHandler<T: Instance> {
     _phantom: PhantomData<T>,
@@ -25,16 +60,68 @@ pub struct InterruptHandler<T: Instance> {
 
 impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
     unsafe fn on_interrupt() {
-        if T::regs().isr().read().eoc() {
+        let isr = T::regs().isr().read();
+
+        if T::state().ring.len() != 0 {
+            ringbuffered_v1::on_interrupt::<T>();
+        } else if isr.eoc() {
             T::regs().ier().modify(|w| w.set_eocie(false));
-        } else {
-            return;
+            T::state().waker.wake();
         }
 
-        T::state().waker.wake();
+        if isr.awd() && T::regs().ier().read().awdie() {
+            T::regs().ier().modify(|w| w.set_awdie(false));
+            T::state().watchdog_waker.wake();
+        }
     }
 }
 
+/// Monitor `channels`, flagging results outside `low..=high`.
+pub(super) fn set_watchdog<T: Instance>(channels: WatchdogChannels, low: u16, high: u16) {
+    assert!(low <= high && high <= 0xFFF);
+
+    let r = T::regs();
+    r.tr().write(|reg| {
+        reg.set_lt(low);
+        reg.set_ht(high);
+    });
+    r.cfgr1().modify(|reg| {
+        match channels {
+            WatchdogChannels::All => reg.set_awdsgl(false),
+            WatchdogChannels::Single(ch) => {
+                reg.set_awdsgl(true);
+                reg.set_awdch(ch);
+            }
+        }
+        reg.set_awden(true);
+    });
+}
+
+pub(super) fn disable_watchdog<T: Instance>() {
+    T::regs().cfgr1().modify(|reg| reg.set_awden(false));
+    T::regs().ier().modify(|w| w.set_awdie(false));
+}
+
+pub(super) async fn wait_for_out_of_window<T: Instance>() {
+    let r = T::regs();
+    r.isr().write(|reg| reg.set_awd(true));
+    r.ier().modify(|w| w.set_awdie(true));
+
+    poll_fn(|cx| {
+        T::state().watchdog_waker.register(cx.waker());
+
+        if r.isr().read().awd() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+
+    r.ier().modify(|w| w.set_awdie(false));
+    r.isr().write(|reg| reg.set_awd(true));
+}
+
 // pub struct Vbat;
 
 // impl AdcChannel<ADC1> for Vbat {}
@@ -59,6 +146,13 @@ impl super::SealedAdcChannel<ADC1> for Vref {
         }
     }
 }
+impl embedded_hal_02::adc::Channel<ADC1> for Vref {
+    type ID = u8;
+
+    fn channel() -> u8 {
+        super::SealedAdcChannel::<ADC1>::channel(&Vref)
+    }
+}
 
 pub struct Temperature;
 impl AdcChannel<ADC1> for Temperature {}
@@ -74,6 +168,13 @@ impl super::SealedAdcChannel<ADC1> for Temperature {
         }
     }
 }
+impl embedded_hal_02::adc::Channel<ADC1> for Temperature {
+    type ID = u8;
+
+    fn channel() -> u8 {
+        super::SealedAdcChannel::<ADC1>::channel(&Temperature)
+    }
+}
 
 impl<'d, T: Instance> Adc<'d, T> {
     pub fn new(
@@ -174,8 +275,10 @@ impl<'d, T: Instance> Adc<'d, T> {
     pub fn enable_vref(&self) -> Vref {
         // Table 28. Embedded internal reference voltage
         // tstart = 10μs
-        T::regs().ccr().modify(|reg| reg.set_vrefen(true));
-        blocking_delay_us(10);
+        if !T::regs().ccr().read().vrefen() {
+            T::regs().ccr().modify(|reg| reg.set_vrefen(true));
+            blocking_delay_us(10);
+        }
         Vref
     }
 
@@ -185,8 +288,10 @@ impl<'d, T: Instance> Adc<'d, T> {
         // 6.3.19 Temperature sensor characteristics
         // tstart ≤ 10μs (parameter tSTART)
         // ts_temp ≥ 4μs
-        T::regs().ccr().modify(|reg| reg.set_tsen(true));
-        blocking_delay_us(10);
+        if !T::regs().ccr().read().tsen() {
+            T::regs().ccr().modify(|reg| reg.set_tsen(true));
+            blocking_delay_us(10);
+        }
         Temperature
     }
 
@@ -200,12 +305,73 @@ impl<'d, T: Instance> Adc<'d, T> {
             .modify(|reg| reg.set_res(resolution.into()));
     }
 
+    /// Start conversions on `edge` of `source`, e.g. a timer TRGO, instead of by software.
+    /// [`Exten::DISABLED`] returns to software start.
+    ///
+    /// Reads wait for the next trigger.
+    pub fn set_external_trigger(&mut self, source: Extsel, edge: Exten) {
+        T::regs().cfgr1().modify(|reg| {
+            reg.set_extsel(source);
+            reg.set_exten(edge);
+        });
+    }
+
+    /// Configure the analog watchdog to monitor `channels`, with `low` and `high` thresholds in
+    /// 12-bit counts. Conversions outside `low..=high` complete
+    /// [`wait_for_out_of_window`](Self::wait_for_out_of_window).
+    ///
+    /// The thresholds stay 12-bit counts whatever [`set_resolution`](Self::set_resolution)
+    /// selected: the watchdog compares them with the conversion at 12-bit scale, so shift a
+    /// lower resolution threshold left by the missing bits.
+    pub fn set_watchdog(&mut self, channels: WatchdogChannels, low: u16, high: u16) {
+        set_watchdog::<T>(channels, low, high);
+    }
+
+    /// Turn the analog watchdog off.
+    pub fn disable_watchdog(&mut self) {
+        disable_watchdog::<T>();
+    }
+
+    /// Wait until a monitored conversion is outside the watchdog window.
+    ///
+    /// The last read channel is converted meanwhile: on every trigger if
+    /// [`set_external_trigger`](Self::set_external_trigger) selected one, or else continuously.
+    pub async fn wait_for_out_of_window(&mut self) {
+        let r = T::regs();
+        let software = r.cfgr1().read().exten() == Exten::DISABLED;
+
+        r.cfgr1().modify(|reg| reg.set_cont(software));
+        r.cr().modify(|reg| reg.set_aden(true));
+        blocking_delay_us(1);
+        r.cr().modify(|reg| reg.set_adstart(true));
+        let _on_drop = OnDrop::new(|| {
+            r.cr().modify(|reg| reg.set_adstp(true));
+            while r.cr().read().adstp() {}
+            r.cfgr1().modify(|reg| reg.set_cont(false));
+        });
+
+        wait_for_out_of_window::<T>().await
+    }
+
     pub fn set_ckmode(&mut self, ckmode: Ckmode) {
         // set ADC clock mode
         T::regs().cfgr2().modify(|reg| reg.set_ckmode(ckmode));
     }
 
     pub async fn read(&mut self, channel: &mut impl AdcChannel<T>) -> u16 {
+        Self::select(channel);
+        self.convert().await
+    }
+
+    pub fn blocking_read(&mut self, channel: &mut impl AdcChannel<T>) -> u16 {
+        Self::select(channel);
+        self.start_conversion(false);
+        while !T::regs().isr().read().eoc() {}
+
+        T::regs().dr().read().data()
+    }
+
+    fn select(channel: &mut impl AdcChannel<T>) {
         let ch_num = channel.channel();
         channel.setup();
 
@@ -216,28 +382,106 @@ impl<'d, T: Instance> Adc<'d, T> {
         T::regs()
             .chselr()
             .write(|reg| reg.set_chselx(ch_num as usize, true));
-
-        self.convert().await
     }
 
-    async fn convert(&mut self) -> u16 {
+    /// Convert `channels` once each, using DMA, and store the results in `readings` in the
+    /// same order.
+    ///
+    /// The scan converts the channels in ascending channel number, so a channel can only
+    /// appear once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is empty, if `readings` is not the same length, or if a channel
+    /// appears twice.
+    #[cfg(dma)]
+    pub async fn read_sequence(
+        &mut self,
+        channels: &mut [AnyAdcChannel<T>],
+        readings: &mut [u16],
+        dma: Peri<'_, impl RxDma<T>>,
+    ) {
+        // CHSELR has a bit for each of the channels 0 to 18.
+        const MAX_CHANNELS: usize = 19;
+
+        assert!(!channels.is_empty(), "Sequence is empty");
+        assert_eq!(
+            channels.len(),
+            readings.len(),
+            "Need one reading per channel"
+        );
+
+        #[cfg(adc_v1b)]
+        T::regs().cr().modify(|reg| reg.set_addis(true));
+
+        T::regs().chselr().write(|reg| {
+            for channel in channels.iter() {
+                assert!(
+                    !reg.chselx(channel.channel() as usize),
+                    "Channel in sequence twice"
+                );
+                reg.set_chselx(channel.channel() as usize, true);
+            }
+        });
+
         T::regs().isr().modify(|reg| {
             reg.set_eoc(true);
             reg.set_eosmp(true);
+            reg.set_ovr(true);
         });
-
         T::regs()
             .smpr()
             .modify(|reg| reg.set_smp(self.sample_time.into()));
-        T::regs().ier().modify(|w| w.set_eocie(true));
+        T::regs().cfgr1().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_dmacfg(Dmacfg::ONESHOT);
+            reg.set_dmaen(true);
+        });
 
-        // AN1011_PY32F030_PY32F003_PY32F002A系列_ADC应用注意事项.pdf
-        // When the ADC is in single-shot mode, after the conversion is completed, the ADC module needs to be re-enabled (ADC_EN = 1) to start the next conversion
-        // (the time interval from ADC_EN set to 1 to ADSTART set to 1 should be greater than 8 ADC clocks).
+        let request = dma.request();
+        let transfer = unsafe {
+            Transfer::new_read(
+                dma,
+                request,
+                T::regs().dr().as_ptr() as *mut u16,
+                readings,
+                Default::default(),
+            )
+        };
+
+        // See `convert`: the ADC is re-enabled before every start.
         T::regs().cr().modify(|reg| reg.set_aden(true));
         blocking_delay_us(1);
         T::regs().cr().modify(|reg| reg.set_adstart(true));
 
+        // Stop the sequence if this future is dropped, and leave DMA off for `read`.
+        let on_drop = OnDrop::new(|| {
+            let r = T::regs();
+            if r.cr().read().adstart() {
+                r.cr().modify(|reg| reg.set_adstp(true));
+                while r.cr().read().adstp() {}
+            }
+            r.cfgr1().modify(|reg| reg.set_dmaen(false));
+        });
+
+        transfer.await;
+        drop(on_drop);
+
+        // Put the readings, taken in ascending channel order, in the order of `channels`.
+        let mut sorted = [0; MAX_CHANNELS];
+        sorted[..readings.len()].copy_from_slice(readings);
+        for (reading, channel) in readings.iter_mut().zip(channels.iter()) {
+            let rank = channels
+                .iter()
+                .filter(|other| other.channel() < channel.channel())
+                .count();
+            *reading = sorted[rank];
+        }
+    }
+
+    async fn convert(&mut self) -> u16 {
+        self.start_conversion(true);
+
         poll_fn(|cx| {
             T::state().waker.register(cx.waker());
 
@@ -251,6 +495,25 @@ impl<'d, T: Instance> Adc<'d, T> {
 
         T::regs().dr().read().data()
     }
+
+    fn start_conversion(&mut self, interrupt: bool) {
+        T::regs().isr().modify(|reg| {
+            reg.set_eoc(true);
+            reg.set_eosmp(true);
+        });
+
+        T::regs()
+            .smpr()
+            .modify(|reg| reg.set_smp(self.sample_time.into()));
+        T::regs().ier().modify(|w| w.set_eocie(interrupt));
+
+        // AN1011_PY32F030_PY32F003_PY32F002A系列_ADC应用注意事项.pdf
+        // When the ADC is in single-shot mode, after the conversion is completed, the ADC module needs to be re-enabled (ADC_EN = 1) to start the next conversion
+        // (the time interval from ADC_EN set to 1 to ADSTART set to 1 should be greater than 8 ADC clocks).
+        T::regs().cr().modify(|reg| reg.set_aden(true));
+        blocking_delay_us(1);
+        T::regs().cr().modify(|reg| reg.set_adstart(true));
+    }
 }
 
 impl<'d, T: Instance> Drop for Adc<'d, T> {
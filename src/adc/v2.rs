@@ -6,10 +6,16 @@ use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
+
 use crate::Peri;
 
 use super::blocking_delay_us;
-use crate::adc::{Adc, AdcChannel, Instance, Resolution, SampleTime};
+use crate::adc::{
+    Adc, AdcChannel, AnyAdcChannel, Instance, Resolution, RxDma, SampleTime, SealedAdcChannel,
+    WatchdogChannels,
+};
+use crate::dma::Transfer;
 use crate::interrupt::typelevel::Interrupt;
 use crate::pac::adc::vals::Extsel;
 use crate::pac::RCC;
@@ -18,12 +24,29 @@ use crate::time::Hertz;
 use crate::{interrupt, rcc};
 
 mod ringbuffered_v2;
-pub use ringbuffered_v2::{RingBufferedAdc, Sequence};
+pub use ringbuffered_v2::{OverrunError, RingBufferedAdc, Sequence, TriggerTimer};
 
 /// Default VREF voltage used for sample conversion to millivolts.
 pub const VREF_DEFAULT_MV: u32 = 3300;
 /// VREF voltage used for factory calibration of VREFINTCAL register.
 pub const VREF_CALIB_MV: u32 = 3300;
+/// Internal voltage reference, in millivolts.
+///
+/// This is the nominal value: the factory calibration in the system memory only covers the
+/// temperature sensor, there is no VREFINT calibration word on these chips.
+pub const VREF_INT: u32 = 1200;
+
+/// Factory calibration of the temperature sensor: 12-bit readings at 30 °C and 85 °C.
+///
+/// py32-metapac does not describe the system memory, so the addresses are taken from the
+/// temperature sensor section of the ADC chapter of the PY32F072 reference manual (TS_CAL1
+/// and TS_CAL2 in the "temperature sensor calibration values" table).
+pub(super) fn ts_cal() -> (u16, u16) {
+    const TS_CAL1: *const u16 = 0x1FFF_3228 as *const u16;
+    const TS_CAL2: *const u16 = 0x1FFF_3230 as *const u16;
+
+    unsafe { (TS_CAL1.read_volatile(), TS_CAL2.read_volatile()) }
+}
 
 /// Interrupt handler.
 pub struct InterruptHandler<T: Instance> {
@@ -32,16 +55,66 @@ pub struct InterruptHandler<T: Instance> {
 
 impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
     unsafe fn on_interrupt() {
-        if T::regs().sr().read().eoc() {
+        let sr = T::regs().sr().read();
+
+        if sr.eoc() {
             T::regs().cr1().modify(|reg| reg.set_eocie(false));
-        } else {
-            return;
+            T::state().waker.wake();
         }
 
-        T::state().waker.wake();
+        if sr.awd() && T::regs().cr1().read().awdie() {
+            T::regs().cr1().modify(|reg| reg.set_awdie(false));
+            T::state().watchdog_waker.wake();
+        }
     }
 }
 
+/// Monitor `channels` of regular conversions, flagging results outside `low..=high`.
+pub(super) fn set_watchdog<T: Instance>(channels: WatchdogChannels, low: u16, high: u16) {
+    assert!(low <= high && high <= 0xFFF);
+
+    let r = T::regs();
+    r.ltr().write(|reg| reg.set_lt(low));
+    r.htr().write(|reg| reg.set_ht(high));
+    r.cr1().modify(|reg| {
+        match channels {
+            WatchdogChannels::All => reg.set_awdsgl(false),
+            WatchdogChannels::Single(ch) => {
+                reg.set_awdsgl(true);
+                reg.set_awdch(ch);
+            }
+        }
+        reg.set_awden(true);
+    });
+}
+
+pub(super) fn disable_watchdog<T: Instance>() {
+    T::regs().cr1().modify(|reg| {
+        reg.set_awden(false);
+        reg.set_awdie(false);
+    });
+}
+
+pub(super) async fn wait_for_out_of_window<T: Instance>() {
+    let r = T::regs();
+    r.sr().modify(|reg| reg.set_awd(false));
+    r.cr1().modify(|reg| reg.set_awdie(true));
+
+    poll_fn(|cx| {
+        T::state().watchdog_waker.register(cx.waker());
+
+        if r.sr().read().awd() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+
+    r.cr1().modify(|reg| reg.set_awdie(false));
+    r.sr().modify(|reg| reg.set_awd(false));
+}
+
 pub struct VrefInt;
 impl AdcChannel<ADC1> for VrefInt {}
 impl super::SealedAdcChannel<ADC1> for VrefInt {
@@ -49,6 +122,13 @@ impl super::SealedAdcChannel<ADC1> for VrefInt {
         17
     }
 }
+impl embedded_hal_02::adc::Channel<ADC1> for VrefInt {
+    type ID = u8;
+
+    fn channel() -> u8 {
+        super::SealedAdcChannel::<ADC1>::channel(&VrefInt)
+    }
+}
 
 impl VrefInt {
     /// Time needed for internal voltage reference to stabilize
@@ -64,6 +144,13 @@ impl super::SealedAdcChannel<ADC1> for Temperature {
         16
     }
 }
+impl embedded_hal_02::adc::Channel<ADC1> for Temperature {
+    type ID = u8;
+
+    fn channel() -> u8 {
+        super::SealedAdcChannel::<ADC1>::channel(&Temperature)
+    }
+}
 
 impl Temperature {
     /// Time needed for temperature sensor readings to stabilize
@@ -179,6 +266,52 @@ where
         T::regs().cr1().modify(|reg| reg.set_res(resolution.into()));
     }
 
+    /// Start regular conversions on the rising edge of `source`, e.g. a timer TRGO or EXTI
+    /// line 11, instead of by software. [`Extsel::SWSTART`] returns to software start.
+    ///
+    /// The trigger edge is fixed on this ADC. Reads wait for the next trigger.
+    pub fn set_external_trigger(&mut self, source: Extsel) {
+        T::regs().cr2().modify(|reg| {
+            reg.set_extsel(source);
+            reg.set_exttrig(true);
+        });
+    }
+
+    /// Configure the analog watchdog to monitor `channels`, with `low` and `high` thresholds in
+    /// 12-bit counts. Conversions outside `low..=high` complete
+    /// [`wait_for_out_of_window`](Self::wait_for_out_of_window).
+    ///
+    /// The thresholds stay 12-bit counts whatever [`set_resolution`](Self::set_resolution)
+    /// selected: the watchdog compares them with the conversion at 12-bit scale, so shift a
+    /// lower resolution threshold left by the missing bits.
+    pub fn set_watchdog(&mut self, channels: WatchdogChannels, low: u16, high: u16) {
+        set_watchdog::<T>(channels, low, high);
+    }
+
+    /// Turn the analog watchdog off.
+    pub fn disable_watchdog(&mut self) {
+        disable_watchdog::<T>();
+    }
+
+    /// Wait until a monitored conversion is outside the watchdog window.
+    ///
+    /// The last read channel is converted meanwhile: on every trigger if
+    /// [`set_external_trigger`](Self::set_external_trigger) selected one, or else continuously.
+    /// Needs the ADC interrupt, see [`new_async`](Self::new_async).
+    pub async fn wait_for_out_of_window(&mut self) {
+        let r = T::regs();
+        let software = r.cr2().read().extsel() == Extsel::SWSTART;
+
+        r.cr2().modify(|reg| {
+            reg.set_cont(software);
+            reg.set_swstart(true);
+            reg.set_exttrig(true);
+        });
+        let _on_drop = OnDrop::new(|| r.cr2().modify(|reg| reg.set_cont(false)));
+
+        wait_for_out_of_window::<T>().await
+    }
+
     /// Enables internal voltage reference and returns [VrefInt], which can be used in
     /// [Adc::read_internal()] to perform conversion.
     pub fn enable_vrefint(&self) -> VrefInt {
@@ -291,6 +424,81 @@ where
         self.convert_async().await
     }
 
+    /// Convert `channels` once each, in order, using DMA, and store the results in `readings`.
+    ///
+    /// Up to 16 channels can be converted, and a channel can appear more than once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is empty or longer than 16, or if `readings` is not the same
+    /// length.
+    pub async fn read_sequence(
+        &mut self,
+        channels: &mut [AnyAdcChannel<T>],
+        readings: &mut [u16],
+        dma: Peri<'_, impl RxDma<T>>,
+    ) {
+        assert!(
+            (1..=16).contains(&channels.len()),
+            "Sequence must have 1 to 16 channels"
+        );
+        assert_eq!(
+            channels.len(),
+            readings.len(),
+            "Need one reading per channel"
+        );
+
+        let r = T::regs();
+        r.sqr1().modify(|reg| reg.set_l(channels.len() as u8 - 1));
+        for (i, channel) in channels.iter().enumerate() {
+            let ch = channel.channel();
+            match i {
+                0..=5 => r.sqr3().modify(|reg| reg.set_sq(i, ch)),
+                6..=11 => r.sqr2().modify(|reg| reg.set_sq(i - 6, ch)),
+                _ => r.sqr1().modify(|reg| reg.set_sq(i - 12, ch)),
+            }
+            Self::set_channel_sample_time(ch, self.sample_time);
+        }
+
+        r.sr().modify(|reg| {
+            reg.set_eoc(false);
+            reg.set_ovr(false);
+        });
+        r.cr1().modify(|reg| {
+            reg.set_scan(true);
+            reg.set_discen(false);
+        });
+        r.cr2().modify(|reg| {
+            reg.set_cont(false);
+            reg.set_dma(true);
+        });
+        // Back to the single conversions of `read` and `blocking_read`, also if this future is
+        // dropped.
+        let _on_drop = OnDrop::new(|| {
+            r.cr2().modify(|reg| reg.set_dma(false));
+            r.cr1().modify(|reg| reg.set_scan(false));
+            r.sqr1().modify(|reg| reg.set_l(0));
+        });
+
+        let request = dma.request();
+        let transfer = unsafe {
+            Transfer::new_read(
+                dma,
+                request,
+                r.dr().as_ptr() as *mut u16,
+                readings,
+                Default::default(),
+            )
+        };
+
+        r.cr2().modify(|reg| {
+            reg.set_swstart(true);
+            reg.set_exttrig(true);
+        });
+
+        transfer.await;
+    }
+
     fn set_channel_sample_time(ch: u8, sample_time: SampleTime) {
         let sample_time = sample_time.into();
         match ch {
@@ -259,6 +259,38 @@ impl AnyChannel {
         }
     }
 
+    /// Start a one-shot memory-to-peripheral transfer that is not owned by a [`Transfer`].
+    ///
+    /// For drivers that chain transfers from their own interrupt handler: the channel interrupt
+    /// stays disabled, completion is polled with `get_remaining_transfers`, and the channel is
+    /// disabled again with `request_pause`.
+    ///
+    /// Safety: `buf` must stay valid until the transfer is complete or paused.
+    pub(crate) unsafe fn start_write_detached<W: Word>(
+        &self,
+        request: Request,
+        buf: *const [W],
+        peri_addr: *mut W,
+    ) {
+        let options = TransferOptions {
+            complete_transfer_ir: false,
+            ..Default::default()
+        };
+        unsafe {
+            self.configure(
+                request,
+                Dir::MemoryToPeripheral,
+                peri_addr as *const u32,
+                buf as *const W as *mut u32,
+                buf.len(),
+                true,
+                W::size(),
+                options,
+            );
+        }
+        self.start();
+    }
+
     fn start(&self) {
         let info = self.info();
         match self.info().dma {
@@ -296,7 +328,7 @@ impl AnyChannel {
         }
     }
 
-    fn request_pause(&self) {
+    pub(crate) fn request_pause(&self) {
         let info = self.info();
         match self.info().dma {
             DmaInfo::Dma(r) => {
@@ -315,7 +347,7 @@ impl AnyChannel {
         }
     }
 
-    fn get_remaining_transfers(&self) -> u16 {
+    pub(crate) fn get_remaining_transfers(&self) -> u16 {
         let info = self.info();
         match self.info().dma {
             DmaInfo::Dma(r) => r.st(info.num).ndtr().read() as _,
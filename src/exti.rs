@@ -211,21 +211,7 @@ struct ExtiInputFuture<'a> {
 
 impl<'a> ExtiInputFuture<'a> {
     fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
-        critical_section::with(|_| {
-            let pin = pin as usize;
-
-            // The port_sel of GPIOF is 2, but embassy seems to handle this automatically, requiring no extra processing.
-            exticr_regs()
-                .exticr(pin / 4)
-                .modify(|w| w.set_exti(pin % 4, port));
-            EXTI.rtsr().modify(|w| w.set_line(pin, rising));
-            EXTI.ftsr().modify(|w| w.set_line(pin, falling));
-
-            // clear pending bit
-            EXTI.pr().write(|w| w.set_line(pin, true));
-
-            cpu_regs().imr().modify(|w| w.set_line(pin, true));
-        });
+        arm(pin, port, rising, falling);
 
         Self {
             pin,
@@ -236,21 +222,49 @@ impl<'a> ExtiInputFuture<'a> {
 
 impl<'a> Drop for ExtiInputFuture<'a> {
     fn drop(&mut self) {
-        critical_section::with(|_| {
-            let pin = self.pin as _;
-            cpu_regs().imr().modify(|w| w.set_line(pin, false));
-        });
+        disarm(self.pin);
     }
 }
 
+/// Route `pin` of `port` to its EXTI line and unmask the line for one interrupt on the
+/// selected edges. The interrupt handler masks the line again when it fires.
+pub(crate) fn arm(pin: u8, port: u8, rising: bool, falling: bool) {
+    critical_section::with(|_| {
+        let pin = pin as usize;
+
+        // The port_sel of GPIOF is 2, but embassy seems to handle this automatically, requiring no extra processing.
+        exticr_regs()
+            .exticr(pin / 4)
+            .modify(|w| w.set_exti(pin % 4, port));
+        EXTI.rtsr().modify(|w| w.set_line(pin, rising));
+        EXTI.ftsr().modify(|w| w.set_line(pin, falling));
+
+        // clear pending bit
+        EXTI.pr().write(|w| w.set_line(pin, true));
+
+        cpu_regs().imr().modify(|w| w.set_line(pin, true));
+    });
+}
+
+/// Returns true until the line armed by [`arm`] has fired.
+pub(crate) fn is_armed(pin: u8) -> bool {
+    cpu_regs().imr().read().line(pin as _)
+}
+
+/// Mask the EXTI line of `pin`.
+pub(crate) fn disarm(pin: u8) {
+    critical_section::with(|_| {
+        cpu_regs().imr().modify(|w| w.set_line(pin as _, false));
+    });
+}
+
 impl<'a> Future for ExtiInputFuture<'a> {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         EXTI_WAKERS[self.pin as usize].register(cx.waker());
 
-        let imr = cpu_regs().imr().read();
-        if !imr.line(self.pin as _) {
+        if !is_armed(self.pin) {
             Poll::Ready(())
         } else {
             Poll::Pending
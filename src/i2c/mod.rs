@@ -5,6 +5,7 @@
 // https://github.com/embassy-rs/embassy/tree/main/embassy-stm32
 // Special thanks to the Embassy Project and its contributors for their work!
 
+pub mod smbus;
 mod v1;
 
 use core::future::Future;
@@ -18,7 +19,7 @@ use embassy_time::{Duration, Instant};
 
 #[cfg(dma)]
 use crate::dma::ChannelAndRequest;
-use crate::gpio::{AfType, AnyPin, OutputType, SealedPin as _, Speed};
+use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
 use crate::interrupt::typelevel::Interrupt;
 use crate::mode::{Async, Blocking, Mode};
 use crate::rcc::{RccInfo, SealedRccPeripheral};
@@ -45,68 +46,206 @@ pub enum Error {
     ZeroLengthTransfer,
 }
 
+/// Config Error
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The kernel clock is outside 2 MHz to 50 MHz, or below 4 MHz for Fast mode.
+    KernelClock,
+    /// The SCL frequency is above 400 kHz or cannot be reached with the kernel clock.
+    Frequency,
+}
+
+/// SCL low to high time ratio in Fast mode.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Duty {
+    /// 2:1
+    Duty2_1,
+    /// 16:9, which reaches 400 kHz from kernel clocks that are a multiple of 10 MHz.
+    Duty16_9,
+}
+
 /// I2C config
 #[non_exhaustive]
 #[derive(Copy, Clone)]
 pub struct Config {
-    // /// Enable internal pullup on SDA.
-    // ///
-    // /// Using external pullup resistors is recommended for I2C. If you do
-    // /// have external pullups you should not enable this.
-    // pub sda_pullup: bool,
-    // /// Enable internal pullup on SCL.
-    // ///
-    // /// Using external pullup resistors is recommended for I2C. If you do
-    // /// have external pullups you should not enable this.
-    // pub scl_pullup: bool,
-    /// Timeout.
+    /// Enable internal pullup on SDA.
+    ///
+    /// Using external pullup resistors is recommended for I2C. If you do
+    /// have external pullups you should not enable this.
+    pub sda_pullup: bool,
+    /// Enable internal pullup on SCL.
+    ///
+    /// Using external pullup resistors is recommended for I2C. If you do
+    /// have external pullups you should not enable this.
+    pub scl_pullup: bool,
+    /// SCL duty cycle above 100 kHz. Standard mode always uses 1:1.
+    pub duty: Duty,
+    /// Timeout of a frame, on top of the time its bytes take at the SCL frequency.
     #[cfg(feature = "time")]
     pub timeout: embassy_time::Duration,
+    /// Run [`I2c::recover_bus`] when a transfer times out, e.g. because a target holds SDA
+    /// low. The transfer still returns [`Error::Timeout`].
+    pub auto_recover: bool,
+    /// Share the bus with other controllers: wait until the bus is idle before each transfer,
+    /// and retry it after losing arbitration.
+    pub multi_master: bool,
+    /// How often a transfer is retried after losing arbitration in multi-master mode, before
+    /// [`Error::Arbitration`] is returned.
+    pub arbitration_retries: u8,
+    /// Delay before the first retry in multi-master mode, doubled for every further one.
+    #[cfg(feature = "time")]
+    pub arbitration_backoff: embassy_time::Duration,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            // sda_pullup: false,
-            // scl_pullup: false,
+            sda_pullup: false,
+            scl_pullup: false,
+            duty: Duty::Duty2_1,
             #[cfg(feature = "time")]
             timeout: embassy_time::Duration::from_millis(1000),
+            auto_recover: false,
+            multi_master: false,
+            arbitration_retries: 3,
+            #[cfg(feature = "time")]
+            arbitration_backoff: embassy_time::Duration::from_micros(100),
         }
     }
 }
 
 impl Config {
     fn scl_af(&self) -> AfType {
-        return AfType::output(OutputType::OpenDrain, Speed::Medium);
-        // return AfType::output_pull(
-        //     OutputType::OpenDrain,
-        //     Speed::Medium,
-        //     match self.scl_pullup {
-        //         true => Pull::Up,
-        //         false => Pull::None,
-        //     },
-        // );
+        AfType::output_pull(
+            OutputType::OpenDrain,
+            Speed::Medium,
+            match self.scl_pullup {
+                true => Pull::Up,
+                false => Pull::None,
+            },
+        )
     }
 
     fn sda_af(&self) -> AfType {
-        return AfType::output(OutputType::OpenDrain, Speed::Medium);
-        // return AfType::output_pull(
-        //     OutputType::OpenDrain,
-        //     Speed::Medium,
-        //     match self.sda_pullup {
-        //         true => Pull::Up,
-        //         false => Pull::None,
-        //     },
-        // );
+        AfType::output_pull(
+            OutputType::OpenDrain,
+            Speed::Medium,
+            match self.sda_pullup {
+                true => Pull::Up,
+                false => Pull::None,
+            },
+        )
+    }
+}
+
+/// I2C bus role, see [`I2c::into_slave_multimaster`].
+#[allow(private_bounds)]
+pub trait MasterMode: SealedMasterMode {}
+
+trait SealedMasterMode {}
+
+/// Controller (master) only. This is the default.
+pub struct Master;
+
+/// Controller and target (slave): the driver also answers at its own addresses.
+pub struct MultiMaster;
+
+impl SealedMasterMode for Master {}
+impl MasterMode for Master {}
+impl SealedMasterMode for MultiMaster {}
+impl MasterMode for MultiMaster {}
+
+/// I2C address.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// 7-bit address
+    SevenBit(u8),
+    /// 10-bit address
+    TenBit(u16),
+}
+
+impl From<u8> for Address {
+    fn from(addr: u8) -> Self {
+        Self::SevenBit(addr)
+    }
+}
+
+impl From<u16> for Address {
+    fn from(addr: u16) -> Self {
+        Self::TenBit(addr)
+    }
+}
+
+/// Own addresses the target answers at.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OwnAddresses {
+    /// The primary address only.
+    OA1(Address),
+    /// A primary address and a second 7-bit address.
+    Both {
+        /// Primary address
+        oa1: Address,
+        /// Second address, 7-bit only
+        oa2: u8,
+    },
+}
+
+/// Target (slave) address configuration.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlaveAddrConfig {
+    /// Own addresses
+    pub addr: OwnAddresses,
+    /// Also answer the general call address 0x00.
+    pub general_call: bool,
+}
+
+impl SlaveAddrConfig {
+    /// Answer at a single 7-bit address.
+    pub fn basic(addr: u8) -> Self {
+        Self {
+            addr: OwnAddresses::OA1(Address::SevenBit(addr)),
+            general_call: false,
+        }
     }
 }
 
+/// What the controller wants from the target.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveCommandKind {
+    /// The controller writes, answer with [`I2c::respond_to_write`].
+    Write,
+    /// The controller reads, answer with [`I2c::respond_to_read`].
+    Read,
+}
+
+/// Request addressed to the target, returned by [`I2c::listen`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlaveCommand {
+    /// Transfer direction
+    pub kind: SlaveCommandKind,
+    /// Own address the controller used; `SevenBit(0)` for a general call.
+    pub address: Address,
+}
+
 /// I2C driver.
-pub struct I2c<'d, M: Mode> {
+///
+/// `IM` is [`Master`] by default; [`I2c::into_slave_multimaster`] turns the driver into a
+/// [`MultiMaster`] one that also works as a target.
+pub struct I2c<'d, M: Mode, IM: MasterMode = Master> {
     info: &'static Info,
     #[allow(dead_code)]
     state: &'static State,
     kernel_clock: Hertz,
+    freq: Hertz,
+    config: Config,
     scl: Option<Peri<'d, AnyPin>>,
     sda: Option<Peri<'d, AnyPin>>,
     #[cfg(dma)]
@@ -116,10 +255,15 @@ pub struct I2c<'d, M: Mode> {
     #[cfg(feature = "time")]
     timeout: Duration,
     _phantom: PhantomData<M>,
+    _phantom2: PhantomData<IM>,
 }
 
 impl<'d> I2c<'d, Async> {
     /// Create a new I2C driver.
+    ///
+    /// `freq` is the SCL frequency, up to 400 kHz in Fast mode. The kernel clock limits what
+    /// can be reached, see [`I2c::scl_frequency`]; a [`ConfigError`] is returned if `freq` is
+    /// out of reach.
     pub fn new<T: Instance>(
         peri: Peri<'d, T>,
         scl: Peri<'d, impl SclPin<T>>,
@@ -129,7 +273,7 @@ impl<'d> I2c<'d, Async> {
         #[cfg(dma)] rx_dma: Peri<'d, impl RxDma<T>>,
         freq: Hertz,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, ConfigError> {
         Self::new_inner(
             peri,
             new_pin!(scl, config.scl_af()),
@@ -146,13 +290,17 @@ impl<'d> I2c<'d, Async> {
 
 impl<'d> I2c<'d, Blocking> {
     /// Create a new blocking I2C driver.
+    ///
+    /// `freq` is the SCL frequency, up to 400 kHz in Fast mode. The kernel clock limits what
+    /// can be reached, see [`I2c::scl_frequency`]; a [`ConfigError`] is returned if `freq` is
+    /// out of reach.
     pub fn new_blocking<T: Instance>(
         peri: Peri<'d, T>,
         scl: Peri<'d, impl SclPin<T>>,
         sda: Peri<'d, impl SdaPin<T>>,
         freq: Hertz,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, ConfigError> {
         Self::new_inner(
             peri,
             new_pin!(scl, config.scl_af()),
@@ -177,13 +325,15 @@ impl<'d, M: Mode> I2c<'d, M> {
         #[cfg(dma)] rx_dma: Option<ChannelAndRequest<'d>>,
         freq: Hertz,
         config: Config,
-    ) -> Self {
+    ) -> Result<Self, ConfigError> {
         unsafe { T::GlobalInterrupt::enable() };
 
         let mut this = Self {
             info: T::info(),
             state: T::state(),
             kernel_clock: T::frequency(),
+            freq,
+            config,
             scl,
             sda,
             #[cfg(dma)]
@@ -193,25 +343,41 @@ impl<'d, M: Mode> I2c<'d, M> {
             #[cfg(feature = "time")]
             timeout: config.timeout,
             _phantom: PhantomData,
+            _phantom2: PhantomData,
         };
-        this.enable_and_init(freq, config);
-        this
+        this.enable_and_init(freq, config)?;
+        Ok(this)
     }
 
-    fn enable_and_init(&mut self, freq: Hertz, config: Config) {
+    fn enable_and_init(&mut self, freq: Hertz, config: Config) -> Result<(), ConfigError> {
         self.info.rcc.enable_and_reset();
-        self.init(freq, config);
+        self.init(freq, config)
     }
+}
 
+impl<'d, M: Mode, IM: MasterMode> I2c<'d, M, IM> {
     fn timeout(&self) -> Timeout {
         Timeout {
             #[cfg(feature = "time")]
             deadline: Instant::now() + self.timeout,
         }
     }
+
+    /// Deadline for a frame of `len` bytes: the timeout, on top of the time the address and
+    /// the bytes take at the SCL frequency. Long frames then only time out if the bus stalls.
+    fn frame_timeout(&self, len: usize) -> Timeout {
+        #[cfg(not(feature = "time"))]
+        let _ = len;
+        Timeout {
+            #[cfg(feature = "time")]
+            deadline: Instant::now()
+                + self.timeout
+                + Duration::from_micros((len as u64 + 2) * 9 * 1_000_000 / self.freq.0 as u64),
+        }
+    }
 }
 
-impl<'d, M: Mode> Drop for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> Drop for I2c<'d, M, IM> {
     fn drop(&mut self) {
         self.scl.as_ref().map(|x| x.set_as_disconnected());
         self.sda.as_ref().map(|x| x.set_as_disconnected());
@@ -323,7 +489,7 @@ foreach_peripheral!(
     };
 );
 
-impl<'d, M: Mode> embedded_hal_02::blocking::i2c::Read for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_02::blocking::i2c::Read for I2c<'d, M, IM> {
     type Error = Error;
 
     fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
@@ -331,7 +497,7 @@ impl<'d, M: Mode> embedded_hal_02::blocking::i2c::Read for I2c<'d, M> {
     }
 }
 
-impl<'d, M: Mode> embedded_hal_02::blocking::i2c::Write for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_02::blocking::i2c::Write for I2c<'d, M, IM> {
     type Error = Error;
 
     fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
@@ -339,7 +505,7 @@ impl<'d, M: Mode> embedded_hal_02::blocking::i2c::Write for I2c<'d, M> {
     }
 }
 
-impl<'d, M: Mode> embedded_hal_02::blocking::i2c::WriteRead for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_02::blocking::i2c::WriteRead for I2c<'d, M, IM> {
     type Error = Error;
 
     fn write_read(
@@ -368,11 +534,11 @@ impl embedded_hal_1::i2c::Error for Error {
     }
 }
 
-impl<'d, M: Mode> embedded_hal_1::i2c::ErrorType for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_1::i2c::ErrorType for I2c<'d, M, IM> {
     type Error = Error;
 }
 
-impl<'d, M: Mode> embedded_hal_1::i2c::I2c for I2c<'d, M> {
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_1::i2c::I2c for I2c<'d, M, IM> {
     fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
         self.blocking_read(address, read)
     }
@@ -399,8 +565,20 @@ impl<'d, M: Mode> embedded_hal_1::i2c::I2c for I2c<'d, M> {
     }
 }
 
+impl<'d, M: Mode, IM: MasterMode> embedded_hal_1::i2c::I2c<embedded_hal_1::i2c::TenBitAddress>
+    for I2c<'d, M, IM>
+{
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal_1::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.blocking_transaction_10bit(address, operations)
+    }
+}
+
 #[cfg(dma)]
-impl<'d> embedded_hal_async::i2c::I2c for I2c<'d, Async> {
+impl<'d, IM: MasterMode> embedded_hal_async::i2c::I2c for I2c<'d, Async, IM> {
     async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
         self.read(address, read).await
     }
@@ -427,6 +605,19 @@ impl<'d> embedded_hal_async::i2c::I2c for I2c<'d, Async> {
     }
 }
 
+#[cfg(dma)]
+impl<'d, IM: MasterMode> embedded_hal_async::i2c::I2c<embedded_hal_1::i2c::TenBitAddress>
+    for I2c<'d, Async, IM>
+{
+    async fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [embedded_hal_1::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_10bit(address, operations).await
+    }
+}
+
 /// Frame type in I2C transaction.
 ///
 /// This tells each method what kind of framing to use, to generate a (repeated) start condition (ST
@@ -0,0 +1,626 @@
+//! System Management Bus (SMBus)
+//!
+//! SMBus transactions on top of an [`I2c`] controller, with optional packet error checking (PEC).
+//! Words are sent little-endian, as the specification requires.
+
+use core::mem;
+
+#[cfg(feature = "time")]
+use embassy_time::Duration;
+
+use super::*;
+#[cfg(feature = "exti")]
+use crate::exti::ExtiInput;
+
+/// Longest block transfer (SMBus 3.0).
+pub const MAX_BLOCK_LEN: usize = 255;
+
+/// Alert Response Address, read by the host to find out which device asserted SMBALERT#.
+pub const ALERT_RESPONSE_ADDRESS: u8 = 0x0C;
+
+/// t<sub>TIMEOUT,MAX</sub>: targets reset their interface once SCL has been low for 25 ms to
+/// 35 ms, so a frame that takes this much longer than its bytes need on the bus is lost.
+#[cfg(feature = "time")]
+pub const TIMEOUT: Duration = Duration::from_millis(35);
+
+/// Packet error checking.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pec {
+    /// No PEC byte is sent or expected.
+    Disabled,
+    /// The PEC is calculated by the CPU.
+    Software,
+    /// The PEC is calculated by the peripheral. Async transactions cannot read the PEC
+    /// register at the right moment while the DMA runs and calculate it in software instead.
+    Hardware,
+}
+
+/// SMBus host driver.
+///
+/// A frame times out once it takes [`TIMEOUT`] longer than its bytes need at the SCL
+/// frequency, in the blocking and the async methods alike, and the bus is then recovered with
+/// [`I2c::recover_bus`]. This leaves room for the 25 ms a target may stretch the clock over a
+/// whole message (t<sub>LOW:SEXT</sub>). The previous timeout settings are restored by
+/// [`Smbus::free`].
+pub struct Smbus<'d, M: Mode, IM: MasterMode = Master> {
+    i2c: I2c<'d, M, IM>,
+    pec: Pec,
+    #[cfg(feature = "time")]
+    i2c_timeout: Duration,
+    i2c_auto_recover: bool,
+}
+
+/// CRC-8 with polynomial x^8 + x^2 + x + 1, as used for the SMBus PEC.
+pub fn crc8(mut crc: u8, data: &[u8]) -> u8 {
+    for &b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = match crc & 0x80 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x07,
+            };
+        }
+    }
+    crc
+}
+
+/// PEC of a write of `write` to `addr`.
+fn write_pec(addr: u8, write: &[u8]) -> u8 {
+    crc8(crc8(0, &[addr << 1]), write)
+}
+
+/// PEC of a read of `read` from `addr`, preceded by a write of `write` unless it is empty.
+fn read_pec(addr: u8, write: &[u8], read: &[u8]) -> u8 {
+    let mut crc = 0;
+    if !write.is_empty() {
+        crc = write_pec(addr, write);
+    }
+    crc = crc8(crc, &[(addr << 1) | 1]);
+    crc8(crc, read)
+}
+
+impl<'d, M: Mode, IM: MasterMode> Smbus<'d, M, IM> {
+    /// Create an SMBus host on `i2c`.
+    pub fn new(mut i2c: I2c<'d, M, IM>, pec: Pec) -> Self {
+        #[cfg(feature = "time")]
+        let i2c_timeout = mem::replace(&mut i2c.timeout, TIMEOUT);
+        let i2c_auto_recover = mem::replace(&mut i2c.config.auto_recover, true);
+
+        Self {
+            i2c,
+            pec,
+            #[cfg(feature = "time")]
+            i2c_timeout,
+            i2c_auto_recover,
+        }
+    }
+
+    /// Return the I2C driver with its own timeout settings.
+    pub fn free(self) -> I2c<'d, M, IM> {
+        let mut i2c = self.i2c;
+        i2c.info.regs.cr1().modify(|w| w.set_enpec(false));
+        #[cfg(feature = "time")]
+        {
+            i2c.timeout = self.i2c_timeout;
+        }
+        i2c.config.auto_recover = self.i2c_auto_recover;
+        i2c
+    }
+
+    /// Change the packet error checking mode.
+    pub fn set_pec(&mut self, pec: Pec) {
+        self.pec = pec;
+    }
+
+    /// Restart the PEC calculation of the peripheral.
+    fn reset_hw_pec(&mut self) {
+        let regs = self.i2c.info.regs;
+        regs.cr1().modify(|w| w.set_enpec(false));
+        regs.cr1().modify(|w| w.set_enpec(true));
+    }
+
+    /// Write `bytes`, followed by the PEC if enabled.
+    fn blocking_write_pec(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        let timeout = self.i2c.frame_timeout(bytes.len());
+        let res = match self.pec {
+            Pec::Disabled => self.i2c.write_bytes(
+                Address::SevenBit(addr),
+                bytes,
+                timeout,
+                FrameOptions::FirstAndLastFrame,
+            ),
+            Pec::Software | Pec::Hardware => {
+                if self.pec == Pec::Hardware {
+                    self.reset_hw_pec();
+                }
+                self.i2c
+                    .write_bytes(
+                        Address::SevenBit(addr),
+                        bytes,
+                        timeout,
+                        FrameOptions::FirstFrame,
+                    )
+                    .and_then(|()| {
+                        // Every byte has been shifted out, so the PEC register is up to date.
+                        let pec = match self.pec {
+                            Pec::Hardware => self.i2c.info.regs.sr2().read().pec(),
+                            _ => write_pec(addr, bytes),
+                        };
+                        self.i2c.write_bytes(
+                            Address::SevenBit(addr),
+                            &[pec],
+                            self.i2c.frame_timeout(1),
+                            FrameOptions::LastFrame,
+                        )
+                    })
+            }
+        };
+        self.i2c.recover_on_timeout(res)
+    }
+
+    /// Write `write` unless it is empty, then read `read` after a (repeated) start and check
+    /// the PEC if enabled.
+    fn blocking_write_read_pec(
+        &mut self,
+        addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error> {
+        let n = read.len();
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let with_pec = self.pec != Pec::Disabled;
+        let buf = &mut buf[..n + with_pec as usize];
+
+        if self.pec == Pec::Hardware {
+            self.reset_hw_pec();
+        }
+        let res = match write.is_empty() {
+            true => Ok(()),
+            false => self.i2c.write_bytes(
+                Address::SevenBit(addr),
+                write,
+                self.i2c.frame_timeout(write.len()),
+                FrameOptions::FirstFrame,
+            ),
+        }
+        .and_then(|()| {
+            let timeout = self.i2c.frame_timeout(buf.len());
+            self.i2c.blocking_read_timeout(
+                Address::SevenBit(addr),
+                buf,
+                timeout,
+                FrameOptions::FirstAndLastFrame,
+                !write.is_empty(),
+            )
+        });
+        self.i2c.recover_on_timeout(res)?;
+
+        read.copy_from_slice(&buf[..n]);
+        self.check_pec(addr, write, buf)
+    }
+
+    /// Block read: write `write`, then read a byte count and that many bytes into `read`.
+    fn blocking_block_read_pec(
+        &mut self,
+        addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let with_pec = self.pec != Pec::Disabled;
+
+        if self.pec == Pec::Hardware {
+            self.reset_hw_pec();
+        }
+        let res = self
+            .i2c
+            .write_bytes(
+                Address::SevenBit(addr),
+                write,
+                self.i2c.frame_timeout(write.len()),
+                FrameOptions::FirstFrame,
+            )
+            .and_then(|()| {
+                self.i2c.blocking_read_timeout(
+                    Address::SevenBit(addr),
+                    &mut buf[..1],
+                    self.i2c.frame_timeout(1),
+                    FrameOptions::FirstAndNextFrame,
+                    true,
+                )
+            })
+            .and_then(|()| {
+                // A read cannot end without a byte to NACK, so an empty block without PEC
+                // clocks in one extra byte.
+                let count = buf[0] as usize;
+                let end = 1 + (count + with_pec as usize).max(1);
+                let timeout = self.i2c.frame_timeout(end - 1);
+                self.i2c.blocking_read_timeout(
+                    Address::SevenBit(addr),
+                    &mut buf[1..end],
+                    timeout,
+                    FrameOptions::LastFrame,
+                    true,
+                )
+            });
+        self.i2c.recover_on_timeout(res)?;
+
+        let count = buf[0] as usize;
+        self.check_pec(addr, write, &buf[..1 + count + with_pec as usize])?;
+        copy_block(&buf[1..1 + count], read)
+    }
+
+    /// Check the PEC at the end of `read`, which was read from `addr` after writing `write`.
+    fn check_pec(&self, addr: u8, write: &[u8], read: &[u8]) -> Result<(), Error> {
+        match self.pec {
+            Pec::Disabled => Ok(()),
+            Pec::Software => check_software_pec(addr, write, read),
+            // The PEC register also took in the received PEC, which leaves it at zero.
+            Pec::Hardware => match self.i2c.info.regs.sr2().read().pec() {
+                0 => Ok(()),
+                _ => Err(Error::Crc),
+            },
+        }
+    }
+
+    /// Quick command: only the address and the R/W bit, which carries the command.
+    ///
+    /// The peripheral cannot end a read without receiving a byte, so for `read` one byte is
+    /// clocked in and discarded.
+    pub fn blocking_quick_command(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+        match read {
+            true => self.i2c.blocking_read(addr, &mut [0]),
+            false => self.i2c.blocking_write(addr, &[]),
+        }
+    }
+
+    /// Send byte: a single data byte without command code.
+    pub fn blocking_send_byte(&mut self, addr: u8, byte: u8) -> Result<(), Error> {
+        self.blocking_write_pec(addr, &[byte])
+    }
+
+    /// Receive byte: a single data byte without command code.
+    pub fn blocking_receive_byte(&mut self, addr: u8) -> Result<u8, Error> {
+        let mut buf = [0];
+        self.blocking_write_read_pec(addr, &[], &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Write byte.
+    pub fn blocking_write_byte(&mut self, addr: u8, cmd: u8, byte: u8) -> Result<(), Error> {
+        self.blocking_write_pec(addr, &[cmd, byte])
+    }
+
+    /// Read byte.
+    pub fn blocking_read_byte(&mut self, addr: u8, cmd: u8) -> Result<u8, Error> {
+        let mut buf = [0];
+        self.blocking_write_read_pec(addr, &[cmd], &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Write word.
+    pub fn blocking_write_word(&mut self, addr: u8, cmd: u8, word: u16) -> Result<(), Error> {
+        let [lo, hi] = word.to_le_bytes();
+        self.blocking_write_pec(addr, &[cmd, lo, hi])
+    }
+
+    /// Read word.
+    pub fn blocking_read_word(&mut self, addr: u8, cmd: u8) -> Result<u16, Error> {
+        let mut buf = [0; 2];
+        self.blocking_write_read_pec(addr, &[cmd], &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    /// Process call: write a word and read a word back in one transaction.
+    pub fn blocking_process_call(&mut self, addr: u8, cmd: u8, word: u16) -> Result<u16, Error> {
+        let [lo, hi] = word.to_le_bytes();
+        let mut buf = [0; 2];
+        self.blocking_write_read_pec(addr, &[cmd, lo, hi], &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    /// Block write of up to [`MAX_BLOCK_LEN`] bytes.
+    pub fn blocking_block_write(&mut self, addr: u8, cmd: u8, data: &[u8]) -> Result<(), Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let bytes = block_frame(&mut buf, cmd, data)?;
+        self.blocking_write_pec(addr, bytes)
+    }
+
+    /// Block read. Returns the number of bytes the device sent, or [`Error::Overrun`] if they
+    /// do not fit into `read`.
+    pub fn blocking_block_read(
+        &mut self,
+        addr: u8,
+        cmd: u8,
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.blocking_block_read_pec(addr, &[cmd], read)
+    }
+
+    /// Block write-block read process call.
+    pub fn blocking_block_process_call(
+        &mut self,
+        addr: u8,
+        cmd: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let bytes = block_frame(&mut buf, cmd, write)?;
+        self.blocking_block_read_pec(addr, bytes, read)
+    }
+
+    /// Read the Alert Response Address and return the 7-bit address of the device that
+    /// asserted SMBALERT#. If several did, the one with the lowest address wins arbitration
+    /// and releases its alert; call again while the line stays low.
+    pub fn blocking_alert_response(&mut self) -> Result<u8, Error> {
+        Ok(self.blocking_receive_byte(ALERT_RESPONSE_ADDRESS)? >> 1)
+    }
+}
+
+#[cfg(dma)]
+impl<'d, IM: MasterMode> Smbus<'d, Async, IM> {
+    /// Write `bytes`, followed by the PEC if enabled.
+    async fn write_pec(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        let res = async {
+            match self.pec {
+                Pec::Disabled => {
+                    self.i2c
+                        .write_frame(
+                            Address::SevenBit(addr),
+                            bytes,
+                            FrameOptions::FirstAndLastFrame,
+                        )
+                        .await
+                }
+                Pec::Software | Pec::Hardware => {
+                    self.i2c
+                        .write_frame(Address::SevenBit(addr), bytes, FrameOptions::FirstFrame)
+                        .await?;
+                    self.i2c
+                        .write_frame(
+                            Address::SevenBit(addr),
+                            &[write_pec(addr, bytes)],
+                            FrameOptions::LastFrame,
+                        )
+                        .await
+                }
+            }
+        }
+        .await;
+        self.i2c.recover_on_timeout(res)
+    }
+
+    /// Write `write` unless it is empty, then read `read` after a (repeated) start and check
+    /// the PEC if enabled.
+    async fn write_read_pec(
+        &mut self,
+        addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Error> {
+        let n = read.len();
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let with_pec = self.pec != Pec::Disabled;
+        let buf = &mut buf[..n + with_pec as usize];
+
+        let res = async {
+            if !write.is_empty() {
+                self.i2c
+                    .write_frame(Address::SevenBit(addr), write, FrameOptions::FirstFrame)
+                    .await?;
+            }
+            self.i2c
+                .read_frame(
+                    Address::SevenBit(addr),
+                    buf,
+                    FrameOptions::FirstAndLastFrame,
+                    !write.is_empty(),
+                )
+                .await
+        }
+        .await;
+        self.i2c.recover_on_timeout(res)?;
+
+        read.copy_from_slice(&buf[..n]);
+        match with_pec {
+            true => check_software_pec(addr, write, buf),
+            false => Ok(()),
+        }
+    }
+
+    /// Block read: write `write`, then read a byte count and that many bytes into `read`.
+    async fn block_read_pec(
+        &mut self,
+        addr: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let with_pec = self.pec != Pec::Disabled;
+
+        let res = async {
+            self.i2c
+                .write_frame(Address::SevenBit(addr), write, FrameOptions::FirstFrame)
+                .await?;
+            self.i2c
+                .read_frame(
+                    Address::SevenBit(addr),
+                    &mut buf[..1],
+                    FrameOptions::FirstAndNextFrame,
+                    true,
+                )
+                .await?;
+            // A read cannot end without a byte to NACK, so an empty block without PEC clocks
+            // in one extra byte.
+            let end = 1 + (buf[0] as usize + with_pec as usize).max(1);
+            self.i2c
+                .read_frame(
+                    Address::SevenBit(addr),
+                    &mut buf[1..end],
+                    FrameOptions::LastFrame,
+                    true,
+                )
+                .await
+        }
+        .await;
+        self.i2c.recover_on_timeout(res)?;
+
+        let count = buf[0] as usize;
+        if with_pec {
+            check_software_pec(addr, write, &buf[..1 + count + 1])?;
+        }
+        copy_block(&buf[1..1 + count], read)
+    }
+
+    /// Quick command: only the address and the R/W bit, which carries the command.
+    ///
+    /// The peripheral cannot end a read without receiving a byte, so for `read` one byte is
+    /// clocked in and discarded.
+    pub async fn quick_command(&mut self, addr: u8, read: bool) -> Result<(), Error> {
+        match read {
+            true => self.i2c.read(addr, &mut [0]).await,
+            false => self.i2c.write(addr, &[]).await,
+        }
+    }
+
+    /// Send byte: a single data byte without command code.
+    pub async fn send_byte(&mut self, addr: u8, byte: u8) -> Result<(), Error> {
+        self.write_pec(addr, &[byte]).await
+    }
+
+    /// Receive byte: a single data byte without command code.
+    pub async fn receive_byte(&mut self, addr: u8) -> Result<u8, Error> {
+        let mut buf = [0];
+        self.write_read_pec(addr, &[], &mut buf).await?;
+        Ok(buf[0])
+    }
+
+    /// Write byte.
+    pub async fn write_byte(&mut self, addr: u8, cmd: u8, byte: u8) -> Result<(), Error> {
+        self.write_pec(addr, &[cmd, byte]).await
+    }
+
+    /// Read byte.
+    pub async fn read_byte(&mut self, addr: u8, cmd: u8) -> Result<u8, Error> {
+        let mut buf = [0];
+        self.write_read_pec(addr, &[cmd], &mut buf).await?;
+        Ok(buf[0])
+    }
+
+    /// Write word.
+    pub async fn write_word(&mut self, addr: u8, cmd: u8, word: u16) -> Result<(), Error> {
+        let [lo, hi] = word.to_le_bytes();
+        self.write_pec(addr, &[cmd, lo, hi]).await
+    }
+
+    /// Read word.
+    pub async fn read_word(&mut self, addr: u8, cmd: u8) -> Result<u16, Error> {
+        let mut buf = [0; 2];
+        self.write_read_pec(addr, &[cmd], &mut buf).await?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    /// Process call: write a word and read a word back in one transaction.
+    pub async fn process_call(&mut self, addr: u8, cmd: u8, word: u16) -> Result<u16, Error> {
+        let [lo, hi] = word.to_le_bytes();
+        let mut buf = [0; 2];
+        self.write_read_pec(addr, &[cmd, lo, hi], &mut buf).await?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    /// Block write of up to [`MAX_BLOCK_LEN`] bytes.
+    pub async fn block_write(&mut self, addr: u8, cmd: u8, data: &[u8]) -> Result<(), Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let bytes = block_frame(&mut buf, cmd, data)?;
+        self.write_pec(addr, bytes).await
+    }
+
+    /// Block read. Returns the number of bytes the device sent, or [`Error::Overrun`] if they
+    /// do not fit into `read`.
+    pub async fn block_read(&mut self, addr: u8, cmd: u8, read: &mut [u8]) -> Result<usize, Error> {
+        self.block_read_pec(addr, &[cmd], read).await
+    }
+
+    /// Block write-block read process call.
+    pub async fn block_process_call(
+        &mut self,
+        addr: u8,
+        cmd: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut buf = [0; MAX_BLOCK_LEN + 2];
+        let bytes = block_frame(&mut buf, cmd, write)?;
+        self.block_read_pec(addr, bytes, read).await
+    }
+
+    /// Read the Alert Response Address and return the 7-bit address of the device that
+    /// asserted SMBALERT#. If several did, the one with the lowest address wins arbitration
+    /// and releases its alert; call again while the line stays low.
+    pub async fn alert_response(&mut self) -> Result<u8, Error> {
+        Ok(self.receive_byte(ALERT_RESPONSE_ADDRESS).await? >> 1)
+    }
+
+    /// Wait until a device pulls the SMBALERT# line `alert` low, then return its address as
+    /// [`alert_response`](Self::alert_response) does.
+    #[cfg(feature = "exti")]
+    pub async fn wait_for_alert(&mut self, alert: &mut ExtiInput<'_>) -> Result<u8, Error> {
+        alert.wait_for_low().await;
+        self.alert_response().await
+    }
+}
+
+/// Lay out a block transfer `[cmd, count, data...]` in `buf`.
+fn block_frame<'a>(
+    buf: &'a mut [u8; MAX_BLOCK_LEN + 2],
+    cmd: u8,
+    data: &[u8],
+) -> Result<&'a [u8], Error> {
+    if data.len() > MAX_BLOCK_LEN {
+        return Err(Error::Overrun);
+    }
+    buf[0] = cmd;
+    buf[1] = data.len() as u8;
+    buf[2..2 + data.len()].copy_from_slice(data);
+    Ok(&buf[..2 + data.len()])
+}
+
+/// Copy a received block into `read`.
+fn copy_block(block: &[u8], read: &mut [u8]) -> Result<usize, Error> {
+    let Some(read) = read.get_mut(..block.len()) else {
+        return Err(Error::Overrun);
+    };
+    read.copy_from_slice(block);
+    Ok(block.len())
+}
+
+/// Check the PEC at the end of `read`, which was read from `addr` after writing `write`.
+fn check_software_pec(addr: u8, write: &[u8], read: &[u8]) -> Result<(), Error> {
+    let (pec, data) = unwrap!(read.split_last());
+    match read_pec(addr, write, data) == *pec {
+        true => Ok(()),
+        false => Err(Error::Crc),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc8_check_value() {
+        // CRC-8/SMBUS check value
+        assert_eq!(crc8(0, b"123456789"), 0xF4);
+    }
+
+    #[test]
+    fn pec_covers_addresses() {
+        // Read byte from 0x5A, command 0x07, data 0x42
+        let pec = read_pec(0x5A, &[0x07], &[0x42]);
+        assert_eq!(pec, crc8(0, &[0xB4, 0x07, 0xB5, 0x42]));
+        // A correct PEC makes the CRC of the whole message zero.
+        assert_eq!(crc8(0, &[0xB4, 0x07, 0xB5, 0x42, pec]), 0);
+    }
+}
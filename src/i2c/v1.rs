@@ -4,9 +4,8 @@
 // https://github.com/embassy-rs/embassy/tree/main/embassy-stm32
 // Special thanks to the Embassy Project and its contributors for their work!
 
-#[cfg(dma)]
 use core::future::poll_fn;
-#[cfg(dma)]
+use core::mem;
 use core::task::Poll;
 
 use embassy_embedded_hal::SetConfig;
@@ -17,6 +16,7 @@ use embassy_hal_internal::drop::OnDrop;
 use embedded_hal_1::i2c::Operation;
 
 use super::*;
+use crate::gpio::{Flex, SealedPin as _};
 use crate::mode::Mode as PeriMode;
 use crate::pac::i2c;
 
@@ -45,8 +45,15 @@ pub unsafe fn on_interrupt<T: Instance>() {
     });
 }
 
-impl<'d, M: PeriMode> I2c<'d, M> {
-    pub(crate) fn init(&mut self, freq: Hertz, _config: Config) {
+/// First byte of a 10-bit address: `11110`, the two high address bits and R/W = 0.
+fn ten_bit_header(addr: u16) -> u8 {
+    0xF0 | ((addr >> 7) as u8 & 0x06)
+}
+
+impl<'d, M: PeriMode, IM: MasterMode> I2c<'d, M, IM> {
+    pub(crate) fn init(&mut self, freq: Hertz, config: Config) -> Result<(), ConfigError> {
+        let timings = Timings::new(self.kernel_clock, freq, config.duty)?;
+
         self.info.regs.cr1().modify(|reg| {
             reg.set_pe(false);
             //reg.set_anfoff(false);
@@ -78,8 +85,15 @@ impl<'d, M: PeriMode> I2c<'d, M> {
             reg.set_swrst(false);
         });
 
-        let timings = Timings::new(self.kernel_clock, freq);
+        self.set_timings(&timings);
+
+        self.info.regs.cr1().modify(|reg| {
+            reg.set_pe(true);
+        });
+        Ok(())
+    }
 
+    fn set_timings(&self, timings: &Timings) {
         self.info.regs.cr2().modify(|reg| {
             reg.set_freq(timings.freq);
         });
@@ -91,10 +105,167 @@ impl<'d, M: PeriMode> I2c<'d, M> {
         self.info.regs.trise().modify(|reg| {
             reg.set_trise(timings.trise);
         });
+    }
 
-        self.info.regs.cr1().modify(|reg| {
-            reg.set_pe(true);
+    /// SCL frequency actually generated. The clock control register divides the kernel clock
+    /// in whole steps, rounded so that SCL is never faster than requested; the rise time of
+    /// the bus slows it down further.
+    pub fn scl_frequency(&self) -> Hertz {
+        // `freq` was validated by the constructor or `set_config`.
+        let timings = unwrap!(Timings::new(self.kernel_clock, self.freq, self.config.duty));
+        timings.scl_frequency(self.kernel_clock)
+    }
+
+    /// Free a bus held by a target that was reset or disturbed in the middle of a transfer.
+    ///
+    /// The SCL and SDA pins are switched to GPIO, SCL is clocked up to nine times until the
+    /// target releases SDA, and a STOP condition is generated. The pins are then handed back
+    /// to the peripheral, which is reinitialised. Returns [`Error::Bus`] if SCL or SDA are
+    /// still held low afterwards.
+    pub fn recover_bus(&mut self) -> Result<(), Error> {
+        let regs = self.info.regs;
+        regs.cr1().modify(|w| w.set_pe(false));
+
+        // Half a period of a 100 kHz clock
+        let hclk = unsafe { crate::rcc::get_freqs() }
+            .hclk1
+            .to_hertz()
+            .map_or(0, |f| f.0);
+        let delay = || cortex_m::asm::delay(hclk / 200_000);
+
+        let (Some(scl), Some(sda)) = (self.scl.as_mut(), self.sda.as_mut()) else {
+            return Err(Error::Bus);
+        };
+        let af_num = |pin: &Peri<'_, AnyPin>| {
+            let n = pin._pin() as usize;
+            pin.block().afr(n / 8).read().afr(n % 8)
+        };
+        let (scl_af, sda_af) = (af_num(scl), af_num(sda));
+
+        let released = {
+            let mut scl = Flex {
+                pin: scl.reborrow(),
+            };
+            let mut sda = Flex {
+                pin: sda.reborrow(),
+            };
+            scl.set_high();
+            sda.set_high();
+            scl.set_as_input_output(Speed::Medium);
+            sda.set_as_input_output(Speed::Medium);
+            delay();
+
+            for _ in 0..9 {
+                if sda.is_high() {
+                    break;
+                }
+                scl.set_low();
+                delay();
+                scl.set_high();
+                delay();
+                // Give a stretching target a bounded amount of time.
+                for _ in 0..100 {
+                    if scl.is_high() {
+                        break;
+                    }
+                    delay();
+                }
+            }
+
+            // STOP: SDA goes high while SCL is high.
+            scl.set_low();
+            delay();
+            sda.set_low();
+            delay();
+            scl.set_high();
+            delay();
+            sda.set_high();
+            delay();
+
+            scl.is_high() && sda.is_high()
+        };
+
+        if let (Some(scl), Some(sda)) = (&self.scl, &self.sda) {
+            critical_section::with(|_| {
+                scl.set_as_af(scl_af, self.config.scl_af());
+                sda.set_as_af(sda_af, self.config.sda_af());
+            });
+        }
+
+        // The reset clears the own addresses of a target.
+        let (oar1, oar2, cr1) = (regs.oar1().read(), regs.oar2().read(), regs.cr1().read());
+        // `freq` was validated by the constructor or `set_config`.
+        unwrap!(self.init(self.freq, self.config));
+        regs.oar1().write_value(oar1);
+        regs.oar2().write_value(oar2);
+        regs.cr1().modify(|w| {
+            w.set_engc(cr1.engc());
+            w.set_ack(cr1.ack());
         });
+
+        match released {
+            true => Ok(()),
+            false => Err(Error::Bus),
+        }
+    }
+
+    /// Run [`recover_bus`](Self::recover_bus) after a timeout if [`Config::auto_recover`] is set.
+    pub(super) fn recover_on_timeout<R>(&mut self, res: Result<R, Error>) -> Result<R, Error> {
+        if matches!(res, Err(Error::Timeout)) && self.config.auto_recover {
+            if self.recover_bus().is_err() {
+                warn!("I2C: bus still stuck after recovery");
+            }
+        }
+        res
+    }
+
+    /// Whether the bus is in use, by this or another controller.
+    pub fn is_bus_busy(&self) -> bool {
+        self.info.regs.sr2().read().busy()
+    }
+
+    /// Whether the bus is idle: no controller holds it and our own STOP has gone out.
+    fn is_bus_idle(&self) -> bool {
+        !self.is_bus_busy() && !self.info.regs.cr1().read().stop()
+    }
+
+    /// Run a controller transfer. With [`Config::multi_master`], wait for the bus to be idle
+    /// first and retry after losing arbitration.
+    fn blocking_arbitrate<R>(
+        &mut self,
+        mut transfer: impl FnMut(&mut Self) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut retries = 0;
+        let res = loop {
+            if self.config.multi_master {
+                let timeout = self.timeout();
+                let idle = loop {
+                    if self.is_bus_idle() {
+                        break Ok(());
+                    }
+                    if let Err(e) = timeout.check() {
+                        break Err(e);
+                    }
+                };
+                if let Err(e) = idle {
+                    break Err(e);
+                }
+            }
+
+            match transfer(self) {
+                Err(Error::Arbitration)
+                    if self.config.multi_master && retries < self.config.arbitration_retries =>
+                {
+                    #[cfg(feature = "time")]
+                    embassy_time::block_for(
+                        self.config.arbitration_backoff * (1 << retries.min(7)),
+                    );
+                    retries += 1;
+                }
+                res => break res,
+            }
+        };
+        self.recover_on_timeout(res)
     }
 
     fn check_and_clear_error_flags(info: &'static Info) -> Result<i2c::regs::Sr1, Error> {
@@ -154,9 +325,75 @@ impl<'d, M: PeriMode> I2c<'d, M> {
         Ok(sr1)
     }
 
-    fn write_bytes(
+    /// Wait until the START condition was generated and check that it was ours.
+    fn blocking_wait_start(&self, timeout: Timeout) -> Result<(), Error> {
+        while !Self::check_and_clear_error_flags(self.info)?.start() {
+            timeout.check()?;
+        }
+
+        // Check if we were the ones to generate START
+        if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
+            return Err(Error::Arbitration);
+        }
+        Ok(())
+    }
+
+    /// Send the address after a START condition and wait until it was acknowledged. ADDR is
+    /// left set for the caller to clear.
+    ///
+    /// A 10-bit address is sent as a header with the two high bits followed by the low byte. A
+    /// read then needs a repeated START and the header once more, or only the latter on a
+    /// `restart` within a transaction that has already addressed the target.
+    fn blocking_send_address(
+        &self,
+        addr: Address,
+        read: bool,
+        restart: bool,
+        timeout: Timeout,
+    ) -> Result<(), Error> {
+        let regs = self.info.regs;
+        let addr = match addr {
+            Address::SevenBit(addr) => {
+                regs.dr().write(|reg| reg.set_dr((addr << 1) | read as u8));
+                return self.blocking_wait_flag(timeout, |sr1| sr1.addr());
+            }
+            Address::TenBit(addr) => addr,
+        };
+        let header = ten_bit_header(addr);
+
+        if !(read && restart) {
+            regs.dr().write(|reg| reg.set_dr(header));
+            self.blocking_wait_flag(timeout, |sr1| sr1.add10())?;
+            regs.dr().write(|reg| reg.set_dr(addr as u8));
+            self.blocking_wait_flag(timeout, |sr1| sr1.addr())?;
+            if !read {
+                return Ok(());
+            }
+
+            let _ = regs.sr2().read();
+            regs.cr1().modify(|reg| reg.set_start(true));
+            self.blocking_wait_start(timeout)?;
+        }
+        regs.dr().write(|reg| reg.set_dr(header | 1));
+        self.blocking_wait_flag(timeout, |sr1| sr1.addr())
+    }
+
+    /// Wait until `flag` is set in SR1.
+    fn blocking_wait_flag(
+        &self,
+        timeout: Timeout,
+        flag: impl Fn(i2c::regs::Sr1) -> bool,
+    ) -> Result<(), Error> {
+        // Check for any I2C errors. If a NACK occurs, the ADDR bit will never be set.
+        while !flag(Self::check_and_clear_error_flags(self.info)?) {
+            timeout.check()?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn write_bytes(
         &mut self,
-        addr: u8,
+        addr: Address,
         bytes: &[u8],
         timeout: Timeout,
         frame: FrameOptions,
@@ -168,25 +405,9 @@ impl<'d, M: PeriMode> I2c<'d, M> {
                 reg.set_start(true);
             });
 
-            // Wait until START condition was generated
-            while !Self::check_and_clear_error_flags(self.info)?.start() {
-                timeout.check()?;
-            }
-
-            // Check if we were the ones to generate START
-            if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
-                return Err(Error::Arbitration);
-            }
-
-            // Set up current address we're trying to talk to
-            self.info.regs.dr().write(|reg| reg.set_dr(addr << 1));
-
-            // Wait until address was sent
-            // Wait for the address to be acknowledged
-            // Check for any I2C errors. If a NACK occurs, the ADDR bit will never be set.
-            while !Self::check_and_clear_error_flags(self.info)?.addr() {
-                timeout.check()?;
-            }
+            // Wait until START condition was generated, then send the address
+            self.blocking_wait_start(timeout)?;
+            self.blocking_send_address(addr, false, false, timeout)?;
 
             // Clear condition by reading SR2
             let _ = self.info.regs.sr2().read();
@@ -243,12 +464,15 @@ impl<'d, M: PeriMode> I2c<'d, M> {
         Ok(value)
     }
 
-    fn blocking_read_timeout(
+    /// Read `buffer`. `restart` tells that the transaction has already addressed the target
+    /// before this frame.
+    pub(super) fn blocking_read_timeout(
         &mut self,
-        addr: u8,
+        addr: Address,
         buffer: &mut [u8],
         timeout: Timeout,
         frame: FrameOptions,
+        restart: bool,
     ) -> Result<(), Error> {
         let Some((last, buffer)) = buffer.split_last_mut() else {
             return Err(Error::Overrun);
@@ -261,24 +485,9 @@ impl<'d, M: PeriMode> I2c<'d, M> {
                 reg.set_ack(true);
             });
 
-            // Wait until START condition was generated
-            while !Self::check_and_clear_error_flags(self.info)?.start() {
-                timeout.check()?;
-            }
-
-            // Check if we were the ones to generate START
-            if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
-                return Err(Error::Arbitration);
-            }
-
-            // Set up current address we're trying to talk to
-            self.info.regs.dr().write(|reg| reg.set_dr((addr << 1) + 1));
-
-            // Wait until address was sent
-            // Wait for the address to be acknowledged
-            while !Self::check_and_clear_error_flags(self.info)?.addr() {
-                timeout.check()?;
-            }
+            // Wait until START condition was generated, then send the address
+            self.blocking_wait_start(timeout)?;
+            self.blocking_send_address(addr, true, restart, timeout)?;
 
             // Clear condition by reading SR2
             let _ = self.info.regs.sr2().read();
@@ -308,15 +517,27 @@ impl<'d, M: PeriMode> I2c<'d, M> {
 
     /// Blocking read.
     pub fn blocking_read(&mut self, addr: u8, read: &mut [u8]) -> Result<(), Error> {
-        self.blocking_read_timeout(addr, read, self.timeout(), FrameOptions::FirstAndLastFrame)
+        self.blocking_arbitrate(|this| {
+            this.blocking_read_timeout(
+                Address::SevenBit(addr),
+                read,
+                this.frame_timeout(read.len()),
+                FrameOptions::FirstAndLastFrame,
+                false,
+            )
+        })
     }
 
     /// Blocking write.
     pub fn blocking_write(&mut self, addr: u8, write: &[u8]) -> Result<(), Error> {
-        self.write_bytes(addr, write, self.timeout(), FrameOptions::FirstAndLastFrame)?;
-
-        // Fallthrough is success
-        Ok(())
+        self.blocking_arbitrate(|this| {
+            this.write_bytes(
+                Address::SevenBit(addr),
+                write,
+                this.frame_timeout(write.len()),
+                FrameOptions::FirstAndLastFrame,
+            )
+        })
     }
 
     /// Blocking write, restart, read.
@@ -332,12 +553,21 @@ impl<'d, M: PeriMode> I2c<'d, M> {
             return Err(Error::Overrun);
         }
 
-        let timeout = self.timeout();
-
-        self.write_bytes(addr, write, timeout, FrameOptions::FirstFrame)?;
-        self.blocking_read_timeout(addr, read, timeout, FrameOptions::FirstAndLastFrame)?;
-
-        Ok(())
+        let addr = Address::SevenBit(addr);
+
+        self.blocking_arbitrate(|this| {
+            let timeout = this.frame_timeout(write.len());
+            this.write_bytes(addr, write, timeout, FrameOptions::FirstFrame)
+                .and_then(|()| {
+                    this.blocking_read_timeout(
+                        addr,
+                        read,
+                        this.frame_timeout(read.len()),
+                        FrameOptions::FirstAndLastFrame,
+                        true,
+                    )
+                })
+        })
     }
 
     /// Blocking transaction with operations.
@@ -350,20 +580,45 @@ impl<'d, M: PeriMode> I2c<'d, M> {
         addr: u8,
         operations: &mut [Operation<'_>],
     ) -> Result<(), Error> {
-        let timeout = self.timeout();
+        self.blocking_transaction_inner(Address::SevenBit(addr), operations)
+    }
 
-        for (op, frame) in operation_frames(operations)? {
-            match op {
-                Operation::Read(read) => self.blocking_read_timeout(addr, read, timeout, frame)?,
-                Operation::Write(write) => self.write_bytes(addr, write, timeout, frame)?,
-            }
-        }
+    /// Blocking transaction with operations, addressing the target with a 10-bit address.
+    ///
+    /// Consecutive operations of same type are merged. See [transaction contract] for details.
+    ///
+    /// [transaction contract]: embedded_hal_1::i2c::I2c::transaction
+    pub fn blocking_transaction_10bit(
+        &mut self,
+        addr: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.blocking_transaction_inner(Address::TenBit(addr), operations)
+    }
 
-        Ok(())
+    fn blocking_transaction_inner(
+        &mut self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.blocking_arbitrate(|this| {
+            for (i, (op, frame)) in operation_frames(operations)?.into_iter().enumerate() {
+                match op {
+                    Operation::Read(read) => {
+                        let timeout = this.frame_timeout(read.len());
+                        this.blocking_read_timeout(addr, read, timeout, frame, i > 0)?
+                    }
+                    Operation::Write(write) => {
+                        let timeout = this.frame_timeout(write.len());
+                        this.write_bytes(addr, write, timeout, frame)?
+                    }
+                }
+            }
+            Ok(())
+        })
     }
 
     // Async
-    #[cfg(dma)]
     #[inline] // pretty sure this should always be inlined
     fn enable_interrupts(info: &'static Info) -> () {
         info.regs.cr2().modify(|w| {
@@ -374,10 +629,85 @@ impl<'d, M: PeriMode> I2c<'d, M> {
 }
 
 #[cfg(dma)]
-impl<'d> I2c<'d, Async> {
-    async fn write_frame(
+impl<'d, IM: MasterMode> I2c<'d, Async, IM> {
+    /// Wait until `flag` is set in SR1.
+    async fn wait_flag(&self, flag: impl Fn(i2c::regs::Sr1) -> bool) -> Result<(), Error> {
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            match Self::check_and_clear_error_flags(self.info) {
+                Err(e) => Poll::Ready(Err(e)),
+                Ok(sr1) => {
+                    if flag(sr1) {
+                        Poll::Ready(Ok(()))
+                    } else {
+                        // When pending, (re-)enable interrupts to wake us up.
+                        Self::enable_interrupts(self.info);
+                        Poll::Pending
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// Wait until the START condition was generated and check that it was ours.
+    async fn wait_start(&self) -> Result<(), Error> {
+        self.wait_flag(|sr1| sr1.start()).await?;
+
+        // Check if we were the ones to generate START
+        if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
+            return Err(Error::Arbitration);
+        }
+        Ok(())
+    }
+
+    /// Send the address after a START condition and wait until it was acknowledged, like
+    /// [`blocking_send_address`](Self::blocking_send_address).
+    async fn send_address(&self, addr: Address, read: bool, restart: bool) -> Result<(), Error> {
+        let regs = self.info.regs;
+        let addr = match addr {
+            Address::SevenBit(addr) => {
+                regs.dr().write(|reg| reg.set_dr((addr << 1) | read as u8));
+                return self.wait_flag(|sr1| sr1.addr()).await;
+            }
+            Address::TenBit(addr) => addr,
+        };
+        let header = ten_bit_header(addr);
+
+        if !(read && restart) {
+            regs.dr().write(|reg| reg.set_dr(header));
+            self.wait_flag(|sr1| sr1.add10()).await?;
+            regs.dr().write(|reg| reg.set_dr(addr as u8));
+            self.wait_flag(|sr1| sr1.addr()).await?;
+            if !read {
+                return Ok(());
+            }
+
+            regs.sr2().read();
+            regs.cr1().modify(|reg| reg.set_start(true));
+            self.wait_start().await?;
+        }
+        regs.dr().write(|reg| reg.set_dr(header | 1));
+        self.wait_flag(|sr1| sr1.addr()).await
+    }
+
+    /// Write `write` as one frame, failing with [`Error::Timeout`] if it does not finish in
+    /// time, e.g. because a target holds SCL low.
+    pub(super) async fn write_frame(
         &mut self,
-        address: u8,
+        address: Address,
+        write: &[u8],
+        frame: FrameOptions,
+    ) -> Result<(), Error> {
+        self.frame_timeout(write.len())
+            .with(self.write_frame_inner(address, write, frame))
+            .await
+    }
+
+    async fn write_frame_inner(
+        &mut self,
+        address: Address,
         write: &[u8],
         frame: FrameOptions,
     ) -> Result<(), Error> {
@@ -408,51 +738,9 @@ impl<'d> I2c<'d, Async> {
                 reg.set_start(true);
             });
 
-            // Wait until START condition was generated
-            poll_fn(|cx| {
-                self.state.waker.register(cx.waker());
-
-                match Self::check_and_clear_error_flags(self.info) {
-                    Err(e) => Poll::Ready(Err(e)),
-                    Ok(sr1) => {
-                        if sr1.start() {
-                            Poll::Ready(Ok(()))
-                        } else {
-                            // When pending, (re-)enable interrupts to wake us up.
-                            Self::enable_interrupts(self.info);
-                            Poll::Pending
-                        }
-                    }
-                }
-            })
-            .await?;
-
-            // Check if we were the ones to generate START
-            if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
-                return Err(Error::Arbitration);
-            }
-
-            // Set up current address we're trying to talk to
-            self.info.regs.dr().write(|reg| reg.set_dr(address << 1));
-
-            // Wait for the address to be acknowledged
-            poll_fn(|cx| {
-                self.state.waker.register(cx.waker());
-
-                match Self::check_and_clear_error_flags(self.info) {
-                    Err(e) => Poll::Ready(Err(e)),
-                    Ok(sr1) => {
-                        if sr1.addr() {
-                            Poll::Ready(Ok(()))
-                        } else {
-                            // When pending, (re-)enable interrupts to wake us up.
-                            Self::enable_interrupts(self.info);
-                            Poll::Pending
-                        }
-                    }
-                }
-            })
-            .await?;
+            // Wait until START condition was generated, then send the address
+            self.wait_start().await?;
+            self.send_address(address, false, false).await?;
 
             // Clear condition by reading SR2
             self.info.regs.sr2().read();
@@ -527,27 +815,91 @@ impl<'d> I2c<'d, Async> {
         Ok(())
     }
 
+    /// Run a controller transfer. With [`Config::multi_master`], wait for the bus to be idle
+    /// first and retry after losing arbitration.
+    async fn arbitrate<R>(
+        &mut self,
+        mut transfer: impl AsyncFnMut(&mut Self) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut retries = 0;
+        let res = loop {
+            if self.config.multi_master {
+                // There is no interrupt for the bus becoming idle.
+                let idle = self.timeout().with(async {
+                    while !self.is_bus_idle() {
+                        embassy_futures::yield_now().await;
+                    }
+                    Ok(())
+                });
+                if let Err(e) = idle.await {
+                    break Err(e);
+                }
+            }
+
+            match transfer(self).await {
+                Err(Error::Arbitration)
+                    if self.config.multi_master && retries < self.config.arbitration_retries =>
+                {
+                    #[cfg(feature = "time")]
+                    embassy_time::Timer::after(
+                        self.config.arbitration_backoff * (1 << retries.min(7)),
+                    )
+                    .await;
+                    retries += 1;
+                }
+                res => break res,
+            }
+        };
+        self.recover_on_timeout(res)
+    }
+
     /// Write.
     pub async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Error> {
-        self.write_frame(address, write, FrameOptions::FirstAndLastFrame)
-            .await?;
-
-        Ok(())
+        self.arbitrate(async |this| {
+            this.write_frame(
+                Address::SevenBit(address),
+                write,
+                FrameOptions::FirstAndLastFrame,
+            )
+            .await
+        })
+        .await
     }
 
     /// Read.
     pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
-        self.read_frame(address, buffer, FrameOptions::FirstAndLastFrame)
-            .await?;
+        self.arbitrate(async |this| {
+            this.read_frame(
+                Address::SevenBit(address),
+                buffer,
+                FrameOptions::FirstAndLastFrame,
+                false,
+            )
+            .await
+        })
+        .await
+    }
 
-        Ok(())
+    /// Read `buffer`. `restart` tells that the transaction has already addressed the target
+    /// before this frame. Like [`write_frame`](Self::write_frame), the frame times out.
+    pub(super) async fn read_frame(
+        &mut self,
+        address: Address,
+        buffer: &mut [u8],
+        frame: FrameOptions,
+        restart: bool,
+    ) -> Result<(), Error> {
+        self.frame_timeout(buffer.len())
+            .with(self.read_frame_inner(address, buffer, frame, restart))
+            .await
     }
 
-    async fn read_frame(
+    async fn read_frame_inner(
         &mut self,
-        address: u8,
+        address: Address,
         buffer: &mut [u8],
         frame: FrameOptions,
+        restart: bool,
     ) -> Result<(), Error> {
         if buffer.is_empty() {
             return Err(Error::Overrun);
@@ -586,54 +938,9 @@ impl<'d> I2c<'d, Async> {
                 reg.set_ack(true);
             });
 
-            // Wait until START condition was generated
-            poll_fn(|cx| {
-                self.state.waker.register(cx.waker());
-
-                match Self::check_and_clear_error_flags(self.info) {
-                    Err(e) => Poll::Ready(Err(e)),
-                    Ok(sr1) => {
-                        if sr1.start() {
-                            Poll::Ready(Ok(()))
-                        } else {
-                            // When pending, (re-)enable interrupts to wake us up.
-                            Self::enable_interrupts(self.info);
-                            Poll::Pending
-                        }
-                    }
-                }
-            })
-            .await?;
-
-            // Check if we were the ones to generate START
-            if self.info.regs.cr1().read().start() || !self.info.regs.sr2().read().msl() {
-                return Err(Error::Arbitration);
-            }
-
-            // Set up current address we're trying to talk to
-            self.info
-                .regs
-                .dr()
-                .write(|reg| reg.set_dr((address << 1) + 1));
-
-            // Wait for the address to be acknowledged
-            poll_fn(|cx| {
-                self.state.waker.register(cx.waker());
-
-                match Self::check_and_clear_error_flags(self.info) {
-                    Err(e) => Poll::Ready(Err(e)),
-                    Ok(sr1) => {
-                        if sr1.addr() {
-                            Poll::Ready(Ok(()))
-                        } else {
-                            // When pending, (re-)enable interrupts to wake us up.
-                            Self::enable_interrupts(self.info);
-                            Poll::Pending
-                        }
-                    }
-                }
-            })
-            .await?;
+            // Wait until START condition was generated, then send the address
+            self.wait_start().await?;
+            self.send_address(address, true, restart).await?;
 
             // 18.3.8: When a single byte must be received: the NACK must be programmed during EV6
             // event, i.e. program ACK=0 when ADDR=1, before clearing ADDR flag.
@@ -723,10 +1030,14 @@ impl<'d> I2c<'d, Async> {
             return Err(Error::Overrun);
         }
 
-        self.write_frame(address, write, FrameOptions::FirstFrame)
-            .await?;
-        self.read_frame(address, read, FrameOptions::FirstAndLastFrame)
-            .await
+        let address = Address::SevenBit(address);
+        self.arbitrate(async |this| {
+            this.write_frame(address, write, FrameOptions::FirstFrame)
+                .await?;
+            this.read_frame(address, read, FrameOptions::FirstAndLastFrame, true)
+                .await
+        })
+        .await
     }
 
     /// Transaction with operations.
@@ -739,14 +1050,262 @@ impl<'d> I2c<'d, Async> {
         addr: u8,
         operations: &mut [Operation<'_>],
     ) -> Result<(), Error> {
-        for (op, frame) in operation_frames(operations)? {
-            match op {
-                Operation::Read(read) => self.read_frame(addr, read, frame).await?,
-                Operation::Write(write) => self.write_frame(addr, write, frame).await?,
+        self.transaction_inner(Address::SevenBit(addr), operations)
+            .await
+    }
+
+    /// Transaction with operations, addressing the target with a 10-bit address.
+    ///
+    /// Consecutive operations of same type are merged. See [transaction contract] for details.
+    ///
+    /// [transaction contract]: embedded_hal_1::i2c::I2c::transaction
+    pub async fn transaction_10bit(
+        &mut self,
+        addr: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.transaction_inner(Address::TenBit(addr), operations)
+            .await
+    }
+
+    async fn transaction_inner(
+        &mut self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        self.arbitrate(async |this| {
+            for (i, (op, frame)) in operation_frames(operations)?.into_iter().enumerate() {
+                match op {
+                    Operation::Read(read) => this.read_frame(addr, read, frame, i > 0).await?,
+                    Operation::Write(write) => this.write_frame(addr, write, frame).await?,
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl<'d, M: PeriMode> I2c<'d, M, Master> {
+    /// Turn the driver into a [`MultiMaster`] one, which also answers as a target (slave) at the
+    /// addresses in `config`. Controller transfers keep working.
+    pub fn into_slave_multimaster(mut self, config: SlaveAddrConfig) -> I2c<'d, M, MultiMaster> {
+        let mut slave = I2c {
+            info: self.info,
+            state: self.state,
+            kernel_clock: self.kernel_clock,
+            freq: self.freq,
+            config: self.config,
+            scl: self.scl.take(),
+            sda: self.sda.take(),
+            #[cfg(dma)]
+            tx_dma: self.tx_dma.take(),
+            #[cfg(dma)]
+            rx_dma: self.rx_dma.take(),
+            #[cfg(feature = "time")]
+            timeout: self.timeout,
+            _phantom: PhantomData,
+            _phantom2: PhantomData,
+        };
+        // Keep the peripheral enabled
+        mem::forget(self);
+
+        slave.init_slave(config);
+        slave
+    }
+}
+
+impl<'d, M: PeriMode> I2c<'d, M, MultiMaster> {
+    fn init_slave(&mut self, config: SlaveAddrConfig) {
+        let regs = self.info.regs;
+        regs.cr1().modify(|w| w.set_pe(false));
+
+        let (OwnAddresses::OA1(oa1) | OwnAddresses::Both { oa1, .. }) = config.addr;
+        regs.oar1().write(|w| match oa1 {
+            Address::SevenBit(addr) => {
+                w.set_add((addr as u16) << 1);
+                w.set_addmode(i2c::vals::Addmode::BIT7);
+            }
+            Address::TenBit(addr) => {
+                w.set_add(addr);
+                w.set_addmode(i2c::vals::Addmode::BIT10);
+            }
+        });
+        regs.oar2().write(|w| {
+            if let OwnAddresses::Both { oa2, .. } = config.addr {
+                w.set_add2(oa2);
+                w.set_endual(true);
             }
+        });
+
+        regs.cr1().modify(|w| {
+            w.set_engc(config.general_call);
+            w.set_nostretch(false);
+            w.set_pe(true);
+        });
+        // ACK is cleared while PE is off.
+        regs.cr1().modify(|w| w.set_ack(true));
+    }
+}
+
+impl<'d> I2c<'d, Async, MultiMaster> {
+    /// Wait until the controller addresses one of the own addresses.
+    ///
+    /// SCL is stretched until the command is answered with [`respond_to_read`] or
+    /// [`respond_to_write`], so answer promptly.
+    ///
+    /// [`respond_to_read`]: Self::respond_to_read
+    /// [`respond_to_write`]: Self::respond_to_write
+    pub async fn listen(&mut self) -> Result<SlaveCommand, Error> {
+        let regs = self.info.regs;
+        regs.cr1().modify(|w| w.set_ack(true));
+
+        self.wait_target(false, |sr1| sr1.addr().then_some(()))
+            .await?;
+
+        // Reading SR2 after SR1 clears ADDR
+        let sr2 = regs.sr2().read();
+        let address = if sr2.gencall() {
+            Address::SevenBit(0)
+        } else if sr2.dualf() {
+            Address::SevenBit(regs.oar2().read().add2())
+        } else {
+            let oar1 = regs.oar1().read();
+            match oar1.addmode() {
+                i2c::vals::Addmode::BIT10 => Address::TenBit(oar1.add()),
+                _ => Address::SevenBit((oar1.add() >> 1) as u8),
+            }
+        };
+        let kind = match sr2.tra() {
+            true => SlaveCommandKind::Read,
+            false => SlaveCommandKind::Write,
+        };
+        Ok(SlaveCommand { kind, address })
+    }
+
+    /// Receive the data of a [`SlaveCommandKind::Write`] into `buf`.
+    ///
+    /// Returns the number of bytes received once the controller sends a STOP or a repeated
+    /// start; a repeated start is returned by the next [`listen`](Self::listen). Bytes beyond
+    /// `buf` are NACKed and the transfer ends with [`Error::Overrun`].
+    pub async fn respond_to_write(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let regs = self.info.regs;
+        let mut n = 0;
+        let mut overrun = false;
+        if buf.is_empty() {
+            regs.cr1().modify(|w| w.set_ack(false));
         }
 
-        Ok(())
+        loop {
+            let sr1 = self
+                .wait_target(true, |sr1| {
+                    (sr1.rxne() || sr1.stopf() || sr1.addr()).then_some(sr1)
+                })
+                .await?;
+
+            // Drain the data register first, the last byte arrives together with STOP.
+            if sr1.rxne() {
+                let byte = regs.dr().read().dr();
+                match buf.get_mut(n) {
+                    Some(slot) => {
+                        *slot = byte;
+                        n += 1;
+                        if n == buf.len() {
+                            regs.cr1().modify(|w| w.set_ack(false));
+                        }
+                    }
+                    None => overrun = true,
+                }
+                continue;
+            }
+            break;
+        }
+
+        // Writing CR1 after reading SR1 clears STOPF.
+        regs.cr1().modify(|w| w.set_ack(true));
+        match overrun {
+            true => Err(Error::Overrun),
+            false => Ok(n),
+        }
+    }
+
+    /// Send `buf` for a [`SlaveCommandKind::Read`].
+    ///
+    /// Each byte is written once the previous one was acknowledged, so no byte is left behind
+    /// in the data register when the controller ends the read with a NACK. If the controller
+    /// reads more than `buf`, it gets `0xFF`. Returns the number of bytes of `buf` sent.
+    pub async fn respond_to_read(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let regs = self.info.regs;
+        let mut n = 0;
+
+        loop {
+            // `listen` left TXE set, the first byte can go out right away.
+            if n > 0 {
+                let sr1 = self
+                    .wait_target(false, |sr1| {
+                        (sr1.btf() || sr1.af() || sr1.stopf() || sr1.addr()).then_some(sr1)
+                    })
+                    .await?;
+
+                if sr1.af() {
+                    // NACK: the controller has read all it wants.
+                    regs.sr1().write(|reg| {
+                        reg.0 = !0;
+                        reg.set_af(false);
+                    });
+                    break;
+                }
+                if !sr1.btf() {
+                    // STOP or repeated start without a NACK; STOPF is cleared by writing CR1.
+                    regs.cr1().modify(|_| {});
+                    break;
+                }
+            }
+
+            regs.dr()
+                .write(|reg| reg.set_dr(buf.get(n).copied().unwrap_or(0xFF)));
+            n += 1;
+        }
+
+        Ok(n.min(buf.len()))
+    }
+
+    /// Wait for `f` to return `Some`, or a bus error. Target mode only uses event interrupts,
+    /// and buffer interrupts if `buf_ir` is set.
+    async fn wait_target<R>(
+        &mut self,
+        buf_ir: bool,
+        mut f: impl FnMut(i2c::regs::Sr1) -> Option<R>,
+    ) -> Result<R, Error> {
+        let regs = self.info.regs;
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            let sr1 = regs.sr1().read();
+            if let Some(r) = f(sr1) {
+                return Poll::Ready(Ok(r));
+            }
+            if sr1.berr() {
+                regs.sr1().write(|reg| {
+                    reg.0 = !0;
+                    reg.set_berr(false);
+                });
+                return Poll::Ready(Err(Error::Bus));
+            }
+            if sr1.ovr() {
+                regs.sr1().write(|reg| {
+                    reg.0 = !0;
+                    reg.set_ovr(false);
+                });
+                return Poll::Ready(Err(Error::Overrun));
+            }
+
+            // When pending, (re-)enable interrupts to wake us up.
+            regs.cr2().modify(|w| w.set_itbufen(buf_ir));
+            Self::enable_interrupts(self.info);
+            Poll::Pending
+        })
+        .await
     }
 }
 
@@ -764,11 +1323,6 @@ impl Mode {
     }
 }
 
-enum Duty {
-    Duty2_1,
-    Duty16_9,
-}
-
 impl Duty {
     fn duty(&self) -> i2c::vals::Duty {
         match self {
@@ -778,6 +1332,13 @@ impl Duty {
     }
 }
 
+/// Highest SCL frequency, in Hz.
+///
+/// The reference manuals of all supported chips specify the I2C for Standard and Fast mode
+/// only. None of them has the Fast-mode Plus drive (FMP bits in SYSCFG or the pads), which
+/// 1 MHz needs, so Fast-mode Plus is not offered.
+const MAX_SCL_FREQ: u32 = 400_000;
+
 struct Timings {
     freq: u8,
     mode: Mode,
@@ -787,85 +1348,73 @@ struct Timings {
 }
 
 impl Timings {
-    fn new(i2cclk: Hertz, speed: Hertz) -> Self {
+    fn new(i2cclk: Hertz, speed: Hertz, duty: Duty) -> Result<Self, ConfigError> {
         // Calculate settings for I2C speed modes
         let speed = speed.0;
         let clock = i2cclk.0;
         let freq = clock / 1_000_000;
-        assert!((2..=50).contains(&freq));
+        if !(2..=50).contains(&freq) {
+            return Err(ConfigError::KernelClock);
+        }
+        if !(1..=MAX_SCL_FREQ).contains(&speed) {
+            return Err(ConfigError::Frequency);
+        }
 
-        // Configure bus frequency into I2C peripheral
-        let trise = if speed <= 100_000 {
-            freq + 1
-        } else {
-            (freq * 300) / 1000 + 1
+        // Maximum rise time: 1000 ns in Standard mode and 300 ns in Fast mode
+        let trise = match speed {
+            ..=100_000 => freq + 1,
+            _ => (freq * 300) / 1000 + 1,
         };
 
-        let mut ccr;
-        let duty;
-        let mode;
-
-        // I2C clock control calculation
-        if speed <= 100_000 {
-            duty = Duty::Duty2_1;
-            mode = Mode::Standard;
-            ccr = {
-                let ccr = clock / (speed * 2);
-                if ccr < 4 {
-                    4
-                } else {
-                    ccr
-                }
-            };
+        // I2C clock control calculation, rounded up so that SCL is never faster than requested
+        let (mode, duty, ccr) = if speed <= 100_000 {
+            (
+                Mode::Standard,
+                Duty::Duty2_1,
+                clock.div_ceil(speed * 2).max(4),
+            )
         } else {
-            const DUTYCYCLE: u8 = 0;
-            mode = Mode::Fast;
-            if DUTYCYCLE == 0 {
-                duty = Duty::Duty2_1;
-                ccr = clock / (speed * 3);
-                ccr = if ccr < 1 { 1 } else { ccr };
-
-                // Set clock to fast mode with appropriate parameters for selected speed (2:1 duty cycle)
-            } else {
-                duty = Duty::Duty16_9;
-                ccr = clock / (speed * 25);
-                ccr = if ccr < 1 { 1 } else { ccr };
-
-                // Set clock to fast mode with appropriate parameters for selected speed (16:9 duty cycle)
+            if freq < 4 {
+                return Err(ConfigError::KernelClock);
             }
+            let ccr = match duty {
+                Duty::Duty2_1 => clock.div_ceil(speed * 3),
+                Duty::Duty16_9 => clock.div_ceil(speed * 25),
+            };
+            (Mode::Fast, duty, ccr)
+        };
+        // CCR is a 12-bit field
+        if ccr > 0xFFF {
+            return Err(ConfigError::Frequency);
         }
 
-        Self {
+        Ok(Self {
             freq: freq as u8,
             trise: trise as u8,
             ccr: ccr as u16,
             duty,
             mode,
-            //prescale: presc_reg,
-            //scll,
-            //sclh,
-            //sdadel,
-            //scldel,
-        }
+        })
+    }
+
+    /// SCL frequency these timings generate, without rise time.
+    fn scl_frequency(&self, i2cclk: Hertz) -> Hertz {
+        let periods = match (&self.mode, self.duty) {
+            (Mode::Standard, _) => 2,
+            (Mode::Fast, Duty::Duty2_1) => 3,
+            (Mode::Fast, Duty::Duty16_9) => 25,
+        };
+        Hertz(i2cclk.0 / (periods * self.ccr as u32))
     }
 }
 
-impl<'d, M: PeriMode> SetConfig for I2c<'d, M> {
+impl<'d, M: PeriMode, IM: MasterMode> SetConfig for I2c<'d, M, IM> {
     type Config = Hertz;
-    type ConfigError = ();
-    fn set_config(&mut self, config: &Self::Config) -> Result<(), ()> {
-        let timings = Timings::new(self.kernel_clock, *config);
-        self.info.regs.cr2().modify(|reg| {
-            reg.set_freq(timings.freq);
-        });
-        self.info.regs.ccr().modify(|reg| {
-            reg.set_f_s(timings.mode.f_s());
-            reg.set_duty(timings.duty.duty());
-            reg.set_ccr(timings.ccr);
-        });
-        self.info.regs.trise().modify(|reg| {
-            reg.set_trise(timings.trise);
-        });
+    type ConfigError = ConfigError;
+    fn set_config(&mut self, config: &Self::Config) -> Result<(), ConfigError> {
+        let timings = Timings::new(self.kernel_clock, *config, self.config.duty)?;
+        self.freq = *config;
+        self.set_timings(&timings);
 
         Ok(())
     }
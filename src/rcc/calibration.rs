@@ -0,0 +1,222 @@
+//! HSI trimming against an external reference clock.
+
+use crate::pac::RCC;
+use crate::time::Hertz;
+use crate::timer::input_capture::InputCapture;
+use crate::timer::low_level::{InputCaptureMode, InputTISelection};
+use crate::timer::{Channel, GeneralInstance4Channel, TimerBits};
+
+/// Largest value of `ICSCR.HSI_TRIM`.
+const HSI_TRIM_MAX: u16 = 0x1FFF;
+/// Trim steps searched on each side of the current value.
+///
+/// Keeps the HSI (and a PLL or SYSCLK fed by it) close to nominal while searching.
+const HSI_TRIM_SPAN: u16 = 0x100;
+
+/// Reference clock used by [`calibrate_hsi`].
+///
+/// The reference must reach the capture channel as a square wave, e.g. through MCO looped
+/// back to a timer pin, or through a timer input remap where the chip has one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HsiReference {
+    /// 32.768 kHz LSE crystal.
+    LSE,
+    /// HSE at `freq`, divided by `div` on its way to the timer (e.g. by the MCO prescaler).
+    HSE { freq: Hertz, div: u32 },
+    /// USB start-of-frame pulses, 1 kHz.
+    UsbSof,
+}
+
+impl HsiReference {
+    fn freq(&self) -> Hertz {
+        match *self {
+            Self::LSE => Hertz(32_768),
+            Self::HSE { freq, div } => freq / div,
+            Self::UsbSof => Hertz(1_000),
+        }
+    }
+}
+
+/// HSI calibration error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HsiCalibrationError {
+    /// No reference edges were seen on the capture channel.
+    NoReference,
+    /// A reference period is too short to be resolved with the current timer tick.
+    ReferenceTooFast,
+    /// A reference period overflows the timer counter with the current timer tick.
+    ReferenceTooSlow,
+}
+
+/// Trim the HSI against an external reference.
+///
+/// `capture` must be clocked from the HSI (through SYSCLK, directly or via the PLL), and
+/// `channel` must see the reference edges. Its tick frequency is chosen when creating the
+/// [`InputCapture`]: one reference period has to fit in the counter, while more ticks per
+/// period give a finer measurement.
+///
+/// `ICSCR.HSI_TRIM` is binary searched around its current value, and the best trim is left
+/// in place. Returns the remaining HSI error in ppm, positive when the HSI runs fast.
+///
+/// This busy-waits on the capture flags for about 10ms per search step, so it should run
+/// while nothing else depends on the clock being stable.
+pub fn calibrate_hsi<T: GeneralInstance4Channel>(
+    capture: &mut InputCapture<'_, T>,
+    channel: Channel,
+    reference: HsiReference,
+) -> Result<i32, HsiCalibrationError> {
+    let ref_freq = reference.freq().0;
+    let tick = capture.get_tick_freq().0;
+    let mask = match T::BITS {
+        TimerBits::Bits16 => 0xFFFF,
+        #[cfg(py32f072)]
+        TimerBits::Bits32 => u32::MAX,
+    };
+
+    if ref_freq == 0 || tick / ref_freq < 2 {
+        return Err(HsiCalibrationError::ReferenceTooFast);
+    }
+    // Leave headroom for the HSI being off while searching.
+    if tick / ref_freq >= mask - mask / 4 {
+        return Err(HsiCalibrationError::ReferenceTooSlow);
+    }
+
+    // Measure over ~10ms, but at least a few periods for slow references.
+    let periods = (ref_freq / 100).max(8);
+
+    capture.set_input_ti_selection(channel, InputTISelection::Normal);
+    capture.set_input_capture_mode(channel, InputCaptureMode::Rising);
+    capture.enable(channel);
+
+    let m = Measurement {
+        channel,
+        mask,
+        tick,
+        ref_freq,
+        periods,
+    };
+    let initial = RCC.icscr().read().hsi_trim();
+    let result = search_trim(capture, &m, initial);
+
+    capture.disable(channel);
+
+    match result {
+        Ok((trim, ppm)) => {
+            RCC.icscr().modify(|w| w.set_hsi_trim(trim));
+            debug!("rcc: hsi trim {} -> {} ({} ppm)", initial, trim, ppm);
+            Ok(ppm)
+        }
+        Err(e) => {
+            RCC.icscr().modify(|w| w.set_hsi_trim(initial));
+            Err(e)
+        }
+    }
+}
+
+struct Measurement {
+    channel: Channel,
+    mask: u32,
+    tick: u32,
+    ref_freq: u32,
+    periods: u32,
+}
+
+/// Binary search the trim around `initial`, returning the best trim and its error in ppm.
+fn search_trim<T: GeneralInstance4Channel>(
+    capture: &mut InputCapture<'_, T>,
+    m: &Measurement,
+    initial: u16,
+) -> Result<(u16, i32), HsiCalibrationError> {
+    let low = initial.saturating_sub(HSI_TRIM_SPAN);
+    let mut lo = low;
+    let mut hi = (initial + HSI_TRIM_SPAN).min(HSI_TRIM_MAX);
+
+    // Find the lowest trim at which the HSI is not slow. The trim is monotonic, higher
+    // values give a faster HSI.
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if measure_ppm(capture, m, mid)? >= 0 {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    // The best trim is either that one or the one just below it.
+    let mut best = (lo, measure_ppm(capture, m, lo)?);
+    if lo > low {
+        let below = measure_ppm(capture, m, lo - 1)?;
+        if below.unsigned_abs() < best.1.unsigned_abs() {
+            best = (lo - 1, below);
+        }
+    }
+    Ok(best)
+}
+
+/// Apply `trim` and measure the HSI error in ppm over `m.periods` reference periods.
+fn measure_ppm<T: GeneralInstance4Channel>(
+    capture: &mut InputCapture<'_, T>,
+    m: &Measurement,
+    trim: u16,
+) -> Result<i32, HsiCalibrationError> {
+    let Measurement {
+        channel,
+        mask,
+        tick,
+        ref_freq,
+        periods,
+    } = *m;
+
+    RCC.icscr().modify(|w| w.set_hsi_trim(trim));
+
+    let nominal = tick / ref_freq;
+    // A missed or spurious edge shows up as a period far from nominal, drop those.
+    let accepted = (nominal - nominal / 4)..=(nominal + nominal / 4);
+
+    // Discard a stale capture and the first (partial) period after retrimming.
+    let _ = capture.get_capture_value(channel);
+    let mut last = wait_edge(capture, channel, nominal)?;
+
+    let mut total: u64 = 0;
+    let mut count: u32 = 0;
+    let mut attempts = 0;
+    while count < periods {
+        attempts += 1;
+        if attempts > periods * 2 {
+            return Err(HsiCalibrationError::NoReference);
+        }
+
+        let now = wait_edge(capture, channel, nominal)?;
+        let delta = now.wrapping_sub(last) & mask;
+        last = now;
+        if accepted.contains(&delta) {
+            total += delta as u64;
+            count += 1;
+        }
+    }
+
+    // measured / expected - 1, where expected = tick * periods / ref_freq
+    let expected = tick as u64 * periods as u64;
+    let measured = total * ref_freq as u64;
+    let ppm = (measured as i64 - expected as i64) * 1_000_000 / expected as i64;
+    Ok(ppm as i32)
+}
+
+/// Busy-wait for the next capture on `channel`.
+fn wait_edge<T: GeneralInstance4Channel>(
+    capture: &InputCapture<'_, T>,
+    channel: Channel,
+    nominal: u32,
+) -> Result<u32, HsiCalibrationError> {
+    // Every iteration takes at least one CPU cycle, and the timer never ticks faster than
+    // the CPU, so this waits for at least four reference periods.
+    for _ in 0..nominal * 4 {
+        if capture.get_input_interrupt(channel) {
+            // Reading the capture register clears the flag.
+            return Ok(capture.get_capture_value(channel));
+        }
+    }
+    Err(HsiCalibrationError::NoReference)
+}
@@ -9,10 +9,14 @@ use crate::pac::rcc::vals::Pllsrc;
 use crate::pac::rcc::vals::HseFreq;
 #[cfg(rcc_f072)]
 pub use crate::pac::rcc::vals::Pllmul as PllMul;
+#[cfg(rcc_f072)]
+use crate::pac::rcc::vals::Usbpre;
 pub use crate::pac::rcc::vals::{
     Hpre as AHBPrescaler, HsiFs, Hsidiv, Ppre as APBPrescaler, Sw as Sysclk,
 };
 
+use super::util::{contains, find_div};
+use super::{ClockError, ClockSource};
 use crate::pac::{CONFIGBYTES, FLASH, RCC};
 use crate::time::Hertz;
 
@@ -62,6 +66,17 @@ pub struct Pll {
     pub mul: PllMul,
 }
 
+/// USB clock source.
+#[cfg(rcc_f072)]
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum UsbClockSource {
+    /// PLL output, undivided. The PLL must run at 48MHz.
+    Pll,
+    /// PLL output divided by 1.5. The PLL must run at 72MHz.
+    PllDiv1_5,
+}
+
 /// Clocks configutation
 #[non_exhaustive]
 #[derive(Clone, Copy)]
@@ -75,6 +90,12 @@ pub struct Config {
 
     pub ahb_pre: AHBPrescaler,
     pub apb1_pre: APBPrescaler,
+    /// USB clock source.
+    ///
+    /// `None` selects it from the PLL frequency: undivided at 48MHz, divided by 1.5 at 72MHz,
+    /// and no USB clock otherwise.
+    #[cfg(rcc_f072)]
+    pub usb: Option<UsbClockSource>,
     /// Per-peripheral kernel clock selection muxes
     pub mux: super::mux::ClockMux,
     // pub ls: super::LsConfig,
@@ -90,6 +111,8 @@ impl Default for Config {
             pll: None,
             ahb_pre: AHBPrescaler::DIV1,
             apb1_pre: APBPrescaler::DIV1,
+            #[cfg(rcc_f072)]
+            usb: None,
             // ls: Default::default(),
             mux: Default::default(),
         }
@@ -110,14 +133,7 @@ pub(crate) unsafe fn init(config: Config) { unsafe {
             w.set_hsi_trim(hsi_trimming_bytes.hsi_trim());
         });
 
-        match value {
-            HsiFs::HSI_4MHZ => Some(Hertz(4_000_000)),
-            HsiFs::HSI_8MHZ => Some(Hertz(8_000_000)),
-            HsiFs::HSI_16MHZ => Some(Hertz(16_000_000)),
-            HsiFs::HSI_22_12MHZ => Some(Hertz(22_120_000)),
-            HsiFs::HSI_24MHZ => Some(Hertz(24_000_000)),
-            _ => unreachable!(),
-        }
+        Some(unwrap!(hsi_freq(value)))
     } else {
         None
     };
@@ -172,7 +188,7 @@ pub(crate) unsafe fn init(config: Config) { unsafe {
             #[cfg(rcc_f072)]
             let out_freq = src_freq * pll.mul;
             assert!(max::PLL_IN.contains(&src_freq));
-            // assert!(max::PLL_OUT.contains(&pll.src.out_freq(pll.mul)));
+            assert!(max::PLL_OUT.contains(&out_freq));
 
             RCC.cr().modify(|w| w.set_pllon(false));
             while RCC.cr().read().pllrdy() {}
@@ -189,15 +205,25 @@ pub(crate) unsafe fn init(config: Config) { unsafe {
         }
     };
 
-    // let usb = match pll {
-    //     Some(Hertz(72_000_000)) => Some(crate::pac::rcc::vals::Usbpre::DIV1_5),
-    //     Some(Hertz(48_000_000)) => Some(crate::pac::rcc::vals::Usbpre::DIV1),
-    //     _ => None,
-    // }
-    // .map(|usbpre| {
-    //     RCC.cfgr().modify(|w| w.set_usbpre(usbpre));
-    //     Hertz(48_000_000)
-    // });
+    #[cfg(rcc_f072)]
+    let usb = match config.usb {
+        Some(src) => Some(src),
+        None => match pll {
+            Some(Hertz(48_000_000)) => Some(UsbClockSource::Pll),
+            Some(Hertz(72_000_000)) => Some(UsbClockSource::PllDiv1_5),
+            _ => None,
+        },
+    }
+    .map(|src| {
+        let pll = unwrap!(pll, "the USB clock is derived from the PLL, which is not enabled");
+        let (usbpre, freq) = match src {
+            UsbClockSource::Pll => (Usbpre::DIV1, pll),
+            UsbClockSource::PllDiv1_5 => (Usbpre::DIV1_5, pll * 2u32 / 3u32),
+        };
+        assert!(max::USB == freq);
+        RCC.cfgr().modify(|w| w.set_usbpre(usbpre));
+        freq
+    });
 
     // Configure sysclk
     let sys = match config.sys {
@@ -210,8 +236,9 @@ pub(crate) unsafe fn init(config: Config) { unsafe {
     let hclk1 = sys / config.ahb_pre;
     let (pclk1, pclk1_tim) = super::util::calc_pclk(hclk1, config.apb1_pre);
 
-    // assert!(max::HCLK.contains(&hclk));
-    // assert!(max::PCLK.contains(&pclk));
+    assert!(max::SYSCLK.contains(&sys));
+    assert!(max::HCLK.contains(&hclk1));
+    assert!(max::PCLK1.contains(&pclk1));
 
     let latency: u8 = match hclk1.0 {
         ..=24_000_000 => 0,
@@ -289,10 +316,187 @@ pub(crate) unsafe fn init(config: Config) { unsafe {
         hsi: hsi_value.into(),
         lse: None.into(),
         pll: pll.into(),
+        #[cfg(rcc_f072)]
+        usb: usb.into(),
     };
     crate::rcc::set_freqs(clocks);
 }}
 
+/// Bring the system clock back after waking up from Stop mode.
+///
+/// Stop mode switches HSE and the PLL off and falls back to HSI. `cr` and `cfgr` are the RCC
+/// registers saved before entering Stop mode; the prescalers and HSI settings are retained.
+#[cfg(feature = "exti")]
+pub(crate) fn reinit_after_stop(
+    cr: crate::pac::rcc::regs::Cr,
+    cfgr: crate::pac::rcc::regs::Cfgr,
+) {
+    if cr.hseon() && !RCC.cr().read().hserdy() {
+        RCC.cr().modify(|w| w.set_hseon(true));
+        while !RCC.cr().read().hserdy() {}
+    }
+    if cr.pllon() && !RCC.cr().read().pllrdy() {
+        RCC.cr().modify(|w| w.set_pllon(true));
+        while !RCC.cr().read().pllrdy() {}
+    }
+    if RCC.cfgr().read().sws() != cfgr.sw() {
+        RCC.cfgr().modify(|w| w.set_sw(cfgr.sw()));
+        while RCC.cfgr().read().sws() != cfgr.sw() {}
+    }
+}
+
+/// Frequency of the HSI at the given frequency selection.
+const fn hsi_freq(fs: HsiFs) -> Option<Hertz> {
+    match fs {
+        HsiFs::HSI_4MHZ => Some(Hertz(4_000_000)),
+        HsiFs::HSI_8MHZ => Some(Hertz(8_000_000)),
+        HsiFs::HSI_16MHZ => Some(Hertz(16_000_000)),
+        HsiFs::HSI_22_12MHZ => Some(Hertz(22_120_000)),
+        HsiFs::HSI_24MHZ => Some(Hertz(24_000_000)),
+        _ => None,
+    }
+}
+
+const HSIDIV: [(Hsidiv, u32); 8] = [
+    (Hsidiv::DIV1, 1),
+    (Hsidiv::DIV2, 2),
+    (Hsidiv::DIV4, 4),
+    (Hsidiv::DIV8, 8),
+    (Hsidiv::DIV16, 16),
+    (Hsidiv::DIV32, 32),
+    (Hsidiv::DIV64, 64),
+    (Hsidiv::DIV128, 128),
+];
+
+const AHB_PRE: [(AHBPrescaler, u32); 9] = [
+    (AHBPrescaler::DIV1, 1),
+    (AHBPrescaler::DIV2, 2),
+    (AHBPrescaler::DIV4, 4),
+    (AHBPrescaler::DIV8, 8),
+    (AHBPrescaler::DIV16, 16),
+    (AHBPrescaler::DIV64, 64),
+    (AHBPrescaler::DIV128, 128),
+    (AHBPrescaler::DIV256, 256),
+    (AHBPrescaler::DIV512, 512),
+];
+
+const APB_PRE: [(APBPrescaler, u32); 5] = [
+    (APBPrescaler::DIV1, 1),
+    (APBPrescaler::DIV2, 2),
+    (APBPrescaler::DIV4, 4),
+    (APBPrescaler::DIV8, 8),
+    (APBPrescaler::DIV16, 16),
+];
+
+// The PY32F030 PLL has a fixed x2 multiplier. The PY32F072 PLLMUL field in RCC_CFGR only
+// selects x2 or x3 (reference manual, RCC chapter), which are all the `PllMul` values.
+#[cfg(rcc_f030)]
+const PLL_MUL: [((), u32); 1] = [((), 2)];
+#[cfg(rcc_f072)]
+const PLL_MUL: [(PllMul, u32); 2] = [(PllMul::MUL2, 2), (PllMul::MUL3, 3)];
+
+/// Solver behind [`super::ClockTree::build`].
+pub(crate) const fn solve(
+    source: ClockSource,
+    sys: Hertz,
+    hclk: Option<Hertz>,
+    pclk: Option<Hertz>,
+) -> Result<Config, ClockError> {
+    let (hsi, hse, src, src_freq) = match source {
+        ClockSource::HSI(fs) => match hsi_freq(fs) {
+            Some(freq) => (Some(fs), None, PllSource::HSI, freq),
+            None => return Err(ClockError::SourceOutOfRange),
+        },
+        ClockSource::HSE(hse) => {
+            let range = match hse.mode {
+                HseMode::Bypass => max::HSE_BYP,
+                HseMode::Oscillator => max::HSE_OSC,
+            };
+            if !contains(&range, hse.freq) {
+                return Err(ClockError::SourceOutOfRange);
+            }
+            (None, Some(hse), PllSource::HSE, hse.freq)
+        }
+    };
+
+    if !contains(&max::SYSCLK, sys) {
+        return Err(ClockError::SysclkTooHigh);
+    }
+
+    // Prefer feeding SYSCLK straight from the oscillator, the PLL is the last resort.
+    let (sw, hsidiv, pll) = 'sys: {
+        match src {
+            PllSource::HSI => {
+                if let Some(div) = find_div(&HSIDIV, src_freq, sys) {
+                    break 'sys (Sysclk::HSI, div, None);
+                }
+            }
+            PllSource::HSE => {
+                if src_freq.0 == sys.0 {
+                    break 'sys (Sysclk::HSE, Hsidiv::DIV1, None);
+                }
+            }
+        }
+
+        let mut i = 0;
+        while i < PLL_MUL.len() {
+            #[allow(unused_variables)]
+            let (mul, n) = PLL_MUL[i];
+            if src_freq.0 * n == sys.0 {
+                if !contains(&max::PLL_IN, src_freq) {
+                    return Err(ClockError::PllInputOutOfRange);
+                }
+                if !contains(&max::PLL_OUT, sys) {
+                    return Err(ClockError::PllOutputOutOfRange);
+                }
+                let pll = Pll {
+                    src,
+                    #[cfg(rcc_f072)]
+                    mul,
+                };
+                break 'sys (Sysclk::PLL, Hsidiv::DIV1, Some(pll));
+            }
+            i += 1;
+        }
+        return Err(ClockError::SysclkUnreachable);
+    };
+
+    let hclk = match hclk {
+        Some(hclk) => hclk,
+        None => sys,
+    };
+    if !contains(&max::HCLK, hclk) {
+        return Err(ClockError::HclkTooHigh);
+    }
+    let Some(ahb_pre) = find_div(&AHB_PRE, sys, hclk) else {
+        return Err(ClockError::HclkUnreachable);
+    };
+
+    let pclk = match pclk {
+        Some(pclk) => pclk,
+        None => hclk,
+    };
+    if !contains(&max::PCLK1, pclk) {
+        return Err(ClockError::PclkTooHigh);
+    }
+    let Some(apb1_pre) = find_div(&APB_PRE, hclk, pclk) else {
+        return Err(ClockError::PclkUnreachable);
+    };
+
+    Ok(Config {
+        hsi,
+        hsidiv,
+        hse,
+        sys: sw,
+        pll,
+        ahb_pre,
+        apb1_pre,
+        #[cfg(rcc_f072)]
+        usb: None,
+        mux: super::mux::ClockMux::default(),
+    })
+}
+
 mod max {
     use core::ops::RangeInclusive;
 
@@ -301,10 +505,85 @@ mod max {
     pub(crate) const HSE_OSC: RangeInclusive<Hertz> = Hertz(4_000_000)..=Hertz(32_000_000);
     pub(crate) const HSE_BYP: RangeInclusive<Hertz> = Hertz(1_000_000)..=Hertz(32_000_000);
 
-    // pub(crate) const HCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(48_000_000);
-    // pub(crate) const PCLK1: RangeInclusive<Hertz> = Hertz(0)..=Hertz(48_000_000);
+    #[cfg(rcc_f030)]
+    pub(crate) const SYSCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(48_000_000);
+    #[cfg(rcc_f030)]
+    pub(crate) const HCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(48_000_000);
+    #[cfg(rcc_f030)]
+    pub(crate) const PCLK1: RangeInclusive<Hertz> = Hertz(0)..=Hertz(48_000_000);
+
+    #[cfg(rcc_f072)]
+    pub(crate) const SYSCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(72_000_000);
+    #[cfg(rcc_f072)]
+    pub(crate) const HCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(72_000_000);
+    #[cfg(rcc_f072)]
+    pub(crate) const PCLK1: RangeInclusive<Hertz> = Hertz(0)..=Hertz(72_000_000);
 
     #[cfg(any(rcc_f030, rcc_f072))]
     pub(crate) const PLL_IN: RangeInclusive<Hertz> = Hertz(16_000_000)..=Hertz(24_000_000);
-    // pub(crate) const PLL_OUT: RangeInclusive<Hertz> = Hertz(16_000_000)..=Hertz(48_000_000);
+    #[cfg(rcc_f030)]
+    pub(crate) const PLL_OUT: RangeInclusive<Hertz> = Hertz(16_000_000)..=Hertz(48_000_000);
+    #[cfg(rcc_f072)]
+    pub(crate) const PLL_OUT: RangeInclusive<Hertz> = Hertz(16_000_000)..=Hertz(72_000_000);
+
+    #[cfg(rcc_f072)]
+    pub(crate) const USB: Hertz = Hertz(48_000_000);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mhz;
+
+    fn hsi(fs: HsiFs) -> ClockSource {
+        ClockSource::HSI(fs)
+    }
+
+    #[test]
+    fn hsi_divided() {
+        let config = solve(hsi(HsiFs::HSI_24MHZ), mhz(12), None, Some(mhz(6))).unwrap();
+        assert!(config.sys == Sysclk::HSI);
+        assert!(config.hsidiv == Hsidiv::DIV2);
+        assert!(config.pll.is_none());
+        assert!(config.ahb_pre == AHBPrescaler::DIV1);
+        assert!(config.apb1_pre == APBPrescaler::DIV2);
+    }
+
+    #[test]
+    fn pll_from_hsi() {
+        let config = solve(hsi(HsiFs::HSI_24MHZ), mhz(48), None, None).unwrap();
+        assert!(config.sys == Sysclk::PLL);
+        assert!(config.pll.unwrap().src == PllSource::HSI);
+        #[cfg(rcc_f072)]
+        assert!(config.pll.unwrap().mul == PllMul::MUL2);
+    }
+
+    #[cfg(rcc_f072)]
+    #[test]
+    fn pll_x3() {
+        let config = solve(hsi(HsiFs::HSI_24MHZ), mhz(72), None, None).unwrap();
+        assert!(config.pll.unwrap().mul == PllMul::MUL3);
+    }
+
+    #[test]
+    fn over_limit() {
+        let res = solve(hsi(HsiFs::HSI_24MHZ), mhz(96), None, None);
+        assert_eq!(res.err(), Some(ClockError::SysclkTooHigh));
+        #[cfg(rcc_f030)]
+        {
+            let res = solve(hsi(HsiFs::HSI_24MHZ), mhz(72), None, None);
+            assert_eq!(res.err(), Some(ClockError::SysclkTooHigh));
+        }
+    }
+
+    #[test]
+    fn unreachable() {
+        let res = solve(hsi(HsiFs::HSI_24MHZ), mhz(30), None, None);
+        assert_eq!(res.err(), Some(ClockError::SysclkUnreachable));
+        // 8 MHz x2 would give 16 MHz, but the PLL input must be at least 16 MHz.
+        let res = solve(hsi(HsiFs::HSI_8MHZ), mhz(16), None, None);
+        assert_eq!(res.err(), Some(ClockError::PllInputOutOfRange));
+        let res = solve(hsi(HsiFs::HSI_24MHZ), mhz(48), Some(mhz(20)), None);
+        assert_eq!(res.err(), Some(ClockError::HclkUnreachable));
+    }
 }
@@ -2,6 +2,8 @@ pub use crate::pac::rcc::vals::{
     Hpre as AHBPrescaler, HsiFs, Hsidiv, Ppre as APBPrescaler, Sw as Sysclk,
 };
 
+use super::util::{contains, find_div};
+use super::{ClockError, ClockSource};
 use crate::pac::{CONFIGBYTES, FLASH, RCC};
 use crate::time::Hertz;
 
@@ -56,10 +58,7 @@ pub(crate) unsafe fn init(config: Config) {
             w.set_hsi_trim(hsi_trimming_bytes.hsi_trim());
         });
 
-        match value {
-            HsiFs::HSI_24MHZ => Some(Hertz(24_000_000)),
-            _ => unreachable!(),
-        }
+        Some(unwrap!(hsi_freq(value)))
     } else {
         None
     };
@@ -81,6 +80,7 @@ pub(crate) unsafe fn init(config: Config) {
             None
         }
         Some(hse) => {
+            assert!(max::HSE.contains(&hse.freq));
             RCC.cr().modify(|w| w.set_hseen(true));
             Some(hse.freq)
         }
@@ -96,6 +96,10 @@ pub(crate) unsafe fn init(config: Config) {
     let hclk1 = sys / config.ahb_pre;
     let (pclk1, pclk1_tim) = super::util::calc_pclk(hclk1, config.apb1_pre);
 
+    assert!(max::SYSCLK.contains(&sys));
+    assert!(max::HCLK.contains(&hclk1));
+    assert!(max::PCLK1.contains(&pclk1));
+
     let latency: u8 = match hclk1.0 {
         ..=24_000_000 => 0,
         _ => 1,
@@ -137,3 +141,171 @@ pub(crate) unsafe fn init(config: Config) {
     };
     crate::rcc::set_freqs(clocks);
 }
+
+/// Bring the system clock back after waking up from Stop mode.
+///
+/// Stop mode switches the HSE input off and falls back to HSI. `cr` and `cfgr` are the RCC
+/// registers saved before entering Stop mode; the prescalers and HSI settings are retained.
+#[cfg(feature = "exti")]
+pub(crate) fn reinit_after_stop(
+    cr: crate::pac::rcc::regs::Cr,
+    cfgr: crate::pac::rcc::regs::Cfgr,
+) {
+    if cr.hseen() {
+        RCC.cr().modify(|w| w.set_hseen(true));
+    }
+    if RCC.cfgr().read().sws() != cfgr.sw() {
+        RCC.cfgr().modify(|w| w.set_sw(cfgr.sw()));
+        while RCC.cfgr().read().sws() != cfgr.sw() {}
+    }
+}
+
+/// Frequency of the HSI at the given frequency selection.
+const fn hsi_freq(fs: HsiFs) -> Option<Hertz> {
+    match fs {
+        HsiFs::HSI_24MHZ => Some(Hertz(24_000_000)),
+        _ => None,
+    }
+}
+
+const HSIDIV: [(Hsidiv, u32); 8] = [
+    (Hsidiv::DIV1, 1),
+    (Hsidiv::DIV2, 2),
+    (Hsidiv::DIV4, 4),
+    (Hsidiv::DIV8, 8),
+    (Hsidiv::DIV16, 16),
+    (Hsidiv::DIV32, 32),
+    (Hsidiv::DIV64, 64),
+    (Hsidiv::DIV128, 128),
+];
+
+const AHB_PRE: [(AHBPrescaler, u32); 9] = [
+    (AHBPrescaler::DIV1, 1),
+    (AHBPrescaler::DIV2, 2),
+    (AHBPrescaler::DIV4, 4),
+    (AHBPrescaler::DIV8, 8),
+    (AHBPrescaler::DIV16, 16),
+    (AHBPrescaler::DIV64, 64),
+    (AHBPrescaler::DIV128, 128),
+    (AHBPrescaler::DIV256, 256),
+    (AHBPrescaler::DIV512, 512),
+];
+
+const APB_PRE: [(APBPrescaler, u32); 5] = [
+    (APBPrescaler::DIV1, 1),
+    (APBPrescaler::DIV2, 2),
+    (APBPrescaler::DIV4, 4),
+    (APBPrescaler::DIV8, 8),
+    (APBPrescaler::DIV16, 16),
+];
+
+/// Solver behind [`super::ClockTree::build`].
+pub(crate) const fn solve(
+    source: ClockSource,
+    sys: Hertz,
+    hclk: Option<Hertz>,
+    pclk: Option<Hertz>,
+) -> Result<Config, ClockError> {
+    if !contains(&max::SYSCLK, sys) {
+        return Err(ClockError::SysclkTooHigh);
+    }
+
+    // There is no PLL: SYSCLK is either the (divided) HSI or the HSE itself.
+    let (hsi, hse, sw, hsidiv) = match source {
+        ClockSource::HSI(fs) => {
+            let Some(freq) = hsi_freq(fs) else {
+                return Err(ClockError::SourceOutOfRange);
+            };
+            let Some(div) = find_div(&HSIDIV, freq, sys) else {
+                return Err(ClockError::SysclkUnreachable);
+            };
+            (Some(fs), None, Sysclk::HSI, div)
+        }
+        ClockSource::HSE(hse) => {
+            if !contains(&max::HSE, hse.freq) {
+                return Err(ClockError::SourceOutOfRange);
+            }
+            if hse.freq.0 != sys.0 {
+                return Err(ClockError::SysclkUnreachable);
+            }
+            (None, Some(hse), Sysclk::HSE, Hsidiv::DIV1)
+        }
+    };
+
+    let hclk = match hclk {
+        Some(hclk) => hclk,
+        None => sys,
+    };
+    if !contains(&max::HCLK, hclk) {
+        return Err(ClockError::HclkTooHigh);
+    }
+    let Some(ahb_pre) = find_div(&AHB_PRE, sys, hclk) else {
+        return Err(ClockError::HclkUnreachable);
+    };
+
+    let pclk = match pclk {
+        Some(pclk) => pclk,
+        None => hclk,
+    };
+    if !contains(&max::PCLK1, pclk) {
+        return Err(ClockError::PclkTooHigh);
+    }
+    let Some(apb1_pre) = find_div(&APB_PRE, hclk, pclk) else {
+        return Err(ClockError::PclkUnreachable);
+    };
+
+    Ok(Config {
+        hsi,
+        hsidiv,
+        hse,
+        sys: sw,
+        ahb_pre,
+        apb1_pre,
+        mux: super::mux::ClockMux::default(),
+    })
+}
+
+mod max {
+    use core::ops::RangeInclusive;
+
+    use crate::time::Hertz;
+
+    pub(crate) const HSE: RangeInclusive<Hertz> = Hertz(1_000_000)..=Hertz(24_000_000);
+
+    pub(crate) const SYSCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(24_000_000);
+    pub(crate) const HCLK: RangeInclusive<Hertz> = Hertz(0)..=Hertz(24_000_000);
+    pub(crate) const PCLK1: RangeInclusive<Hertz> = Hertz(0)..=Hertz(24_000_000);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::mhz;
+
+    #[test]
+    fn hsi_divided() {
+        let source = ClockSource::HSI(HsiFs::HSI_24MHZ);
+        let config = solve(source, mhz(6), None, Some(mhz(3))).unwrap();
+        assert!(config.sys == Sysclk::HSI);
+        assert!(config.hsidiv == Hsidiv::DIV4);
+        assert!(config.apb1_pre == APBPrescaler::DIV2);
+    }
+
+    #[test]
+    fn over_limit() {
+        let source = ClockSource::HSI(HsiFs::HSI_24MHZ);
+        let res = solve(source, mhz(48), None, None);
+        assert_eq!(res.err(), Some(ClockError::SysclkTooHigh));
+    }
+
+    #[test]
+    fn unreachable() {
+        // There is no PLL, SYSCLK can only be the HSI divided by a power of two.
+        let source = ClockSource::HSI(HsiFs::HSI_24MHZ);
+        let res = solve(source, mhz(16), None, None);
+        assert_eq!(res.err(), Some(ClockError::SysclkUnreachable));
+        let source = ClockSource::HSE(Hse { freq: mhz(8) });
+        let res = solve(source, mhz(16), None, None);
+        assert_eq!(res.err(), Some(ClockError::SysclkUnreachable));
+    }
+}
@@ -4,8 +4,10 @@
 // Special thanks to the Embassy Project and its contributors for their work!
 use core::mem::MaybeUninit;
 
+mod calibration;
 #[cfg(mco)]
 mod mco;
+pub use calibration::*;
 use critical_section::CriticalSection;
 #[cfg(mco)]
 pub use mco::*;
@@ -34,9 +36,122 @@ pub struct Clocks {
     pub lse: crate::time::MaybeHertz,
     #[cfg(not(rcc_f002b))]
     pub pll: crate::time::MaybeHertz,
+    #[cfg(rcc_f072)]
+    pub usb: crate::time::MaybeHertz,
     // pub rtc: crate::time::MaybeHertz,
     // pub sys: Option<crate::time::Hertz>,
-    // pub usb: Option<crate::time::Hertz>,
+}
+
+/// Oscillator feeding a [`ClockTree`].
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum ClockSource {
+    /// Internal high-speed oscillator, at the given frequency selection.
+    HSI(HsiFs),
+    /// External high-speed clock.
+    HSE(Hse),
+}
+
+/// Reason why a [`ClockTree`] cannot be realised on this chip.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockError {
+    /// The source frequency is outside the documented range of the oscillator.
+    SourceOutOfRange,
+    /// The requested SYSCLK is above the maximum of this family.
+    SysclkTooHigh,
+    /// No combination of HSI divider or PLL produces the requested SYSCLK from the source.
+    SysclkUnreachable,
+    /// The PLL would be needed, but the source frequency is outside the PLL input range.
+    PllInputOutOfRange,
+    /// The PLL would be needed, but its output frequency is outside the documented range.
+    PllOutputOutOfRange,
+    /// No AHB prescaler divides SYSCLK down to the requested HCLK.
+    HclkUnreachable,
+    /// The requested HCLK is above the maximum of this family.
+    HclkTooHigh,
+    /// No APB prescaler divides HCLK down to the requested PCLK.
+    PclkUnreachable,
+    /// The requested PCLK is above the maximum of this family.
+    PclkTooHigh,
+}
+
+impl ClockError {
+    /// Human readable description, usable from `const` contexts.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::SourceOutOfRange => "rcc: clock source frequency out of range",
+            Self::SysclkTooHigh => "rcc: SYSCLK above the maximum frequency",
+            Self::SysclkUnreachable => "rcc: SYSCLK cannot be derived from the clock source",
+            Self::PllInputOutOfRange => "rcc: PLL input frequency out of range",
+            Self::PllOutputOutOfRange => "rcc: PLL output frequency out of range",
+            Self::HclkUnreachable => "rcc: HCLK cannot be derived from SYSCLK",
+            Self::HclkTooHigh => "rcc: HCLK above the maximum frequency",
+            Self::PclkUnreachable => "rcc: PCLK cannot be derived from HCLK",
+            Self::PclkTooHigh => "rcc: PCLK above the maximum frequency",
+        }
+    }
+}
+
+/// Clock tree solver.
+///
+/// Describes the wanted SYSCLK/HCLK/PCLK frequencies and the oscillator to derive them from.
+/// [`ClockTree::build`] picks the HSI divider, PLL and bus prescalers and checks every
+/// documented limit of the family. It is a `const fn`, so evaluating it in a `const` turns a
+/// clock misconfiguration into a build error instead of a hang at startup:
+///
+/// ```rust,ignore
+/// use py32_hal::rcc::{ClockSource, ClockTree, HsiFs};
+/// use py32_hal::time::mhz;
+///
+/// const RCC: py32_hal::rcc::Config =
+///     match ClockTree::new(ClockSource::HSI(HsiFs::HSI_24MHZ), mhz(48))
+///         .pclk(mhz(24))
+///         .build()
+///     {
+///         Ok(config) => config,
+///         Err(e) => panic!("{}", e.as_str()),
+///     };
+///
+/// let mut cfg: py32_hal::Config = Default::default();
+/// cfg.rcc = RCC;
+/// ```
+#[derive(Clone, Copy)]
+pub struct ClockTree {
+    source: ClockSource,
+    sys: Hertz,
+    hclk: Option<Hertz>,
+    pclk: Option<Hertz>,
+}
+
+impl ClockTree {
+    /// Request `sys` as SYSCLK, derived from `source`.
+    ///
+    /// HCLK and PCLK default to SYSCLK.
+    pub const fn new(source: ClockSource, sys: Hertz) -> Self {
+        Self {
+            source,
+            sys,
+            hclk: None,
+            pclk: None,
+        }
+    }
+
+    /// Request the AHB clock (HCLK) frequency.
+    pub const fn hclk(mut self, hclk: Hertz) -> Self {
+        self.hclk = Some(hclk);
+        self
+    }
+
+    /// Request the APB clock (PCLK) frequency.
+    pub const fn pclk(mut self, pclk: Hertz) -> Self {
+        self.pclk = Some(pclk);
+        self
+    }
+
+    /// Solve the clock tree, returning the matching [`Config`].
+    pub const fn build(&self) -> Result<Config, ClockError> {
+        _version::solve(self.source, self.sys, self.hclk, self.pclk)
+    }
 }
 
 // #[cfg(feature = "low-power")]
@@ -267,8 +382,27 @@ impl RccInfo {
 
 #[allow(unused)]
 mod util {
+    use core::ops::RangeInclusive;
+
     use crate::time::Hertz;
 
+    pub const fn contains(range: &RangeInclusive<Hertz>, freq: Hertz) -> bool {
+        freq.0 >= range.start().0 && freq.0 <= range.end().0
+    }
+
+    /// Find the divider in `table` that turns `from` exactly into `to`.
+    pub const fn find_div<T: Copy>(table: &[(T, u32)], from: Hertz, to: Hertz) -> Option<T> {
+        let mut i = 0;
+        while i < table.len() {
+            let (div, n) = table[i];
+            if from.0 % n == 0 && from.0 / n == to.0 {
+                return Some(div);
+            }
+            i += 1;
+        }
+        None
+    }
+
     pub fn calc_pclk<D>(hclk: Hertz, ppre: D) -> (Hertz, Hertz)
     where
         Hertz: core::ops::Div<D, Output = Hertz>,
@@ -129,6 +129,11 @@ impl<'d, T: GeneralInstance4Channel> InputCapture<'d, T> {
         self.inner.get_input_interrupt(channel)
     }
 
+    /// Get the counter tick frequency, i.e. the unit of capture values.
+    pub fn get_tick_freq(&self) -> Hertz {
+        self.inner.get_tick_freq()
+    }
+
     fn new_future(
         &self,
         channel: Channel,
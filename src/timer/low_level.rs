@@ -15,7 +15,7 @@ use core::mem::ManuallyDrop;
 use crate::pac;
 use embassy_hal_internal::Peri;
 // Re-export useful enums
-pub use pac::timer::vals::{FilterValue, Sms as SlaveMode, Ts as TriggerSource};
+pub use pac::timer::vals::{FilterValue, Mms as MasterMode, Sms as SlaveMode, Ts as TriggerSource};
 
 use super::*;
 use crate::pac::timer::vals;
@@ -358,6 +358,12 @@ impl<'d, T: CoreInstance> Timer<'d, T> {
     pub fn get_clock_frequency(&self) -> Hertz {
         T::frequency()
     }
+
+    /// Get the counter tick frequency (after prescaler is applied).
+    pub fn get_tick_freq(&self) -> Hertz {
+        let psc = self.regs_core().psc().read();
+        T::frequency() / (psc as u32 + 1)
+    }
 }
 
 impl<'d, T: BasicNoCr2Instance> Timer<'d, T> {
@@ -394,6 +400,12 @@ impl<'d, T: BasicInstance> Timer<'d, T> {
     pub fn regs_basic(&self) -> crate::pac::timer::TimBasic {
         unsafe { crate::pac::timer::TimBasic::from_ptr(T::regs()) }
     }
+
+    /// Set the master mode, which selects the event output on TRGO to other peripherals, e.g.
+    /// [`MasterMode::UPDATE`] to start an ADC conversion on every update event.
+    pub fn set_master_mode(&self, mms: MasterMode) {
+        self.regs_basic().cr2().modify(|r| r.set_mms(mms));
+    }
 }
 
 impl<'d, T: GeneralInstance1Channel> Timer<'d, T> {
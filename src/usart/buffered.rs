@@ -7,7 +7,7 @@
 use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::slice;
-use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use core::task::Poll;
 
 use embassy_embedded_hal::SetConfig;
@@ -19,6 +19,8 @@ use super::{
     Config, ConfigError, CtsPin, Error, Info, Instance, Regs, RtsPin, RxPin, TxPin,
     clear_interrupt_flags, configure, rdr, reconfigure, send_break, sr, tdr,
 };
+#[cfg(feature = "exti")]
+use crate::exti;
 use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
 use crate::interrupt::{self, InterruptExt};
 use crate::time::Hertz;
@@ -61,15 +63,26 @@ unsafe fn on_interrupt(r: Regs, state: &'static State) {
             warn!("Overrun error");
         }
         if sr_val.rxne() {
-            let mut rx_writer = state.rx_buf.writer();
-            let buf = rx_writer.push_slice();
-            if !buf.is_empty() {
-                if let Some(byte) = dr {
-                    buf[0] = byte;
-                    rx_writer.push_done(1);
+            let xonxoff = state.xonxoff.load(Ordering::Relaxed);
+            match dr {
+                // Flow control characters are consumed here, the TX part below acts on them.
+                Some(XOFF) if xonxoff => state.tx_paused.store(true, Ordering::Relaxed),
+                Some(XON) if xonxoff => state.tx_paused.store(false, Ordering::Relaxed),
+                _ => {
+                    let mut rx_writer = state.rx_buf.writer();
+                    let buf = rx_writer.push_slice();
+                    if !buf.is_empty() {
+                        if let Some(byte) = dr {
+                            buf[0] = byte;
+                            rx_writer.push_done(1);
+                            if xonxoff {
+                                rx_pushed(state);
+                            }
+                        }
+                    } else {
+                        // FIXME: Should we disable any further RX interrupts when the buffer becomes full.
+                    }
                 }
-            } else {
-                // FIXME: Should we disable any further RX interrupts when the buffer becomes full.
             }
 
             if !state.rx_buf.is_empty() {
@@ -98,6 +111,25 @@ unsafe fn on_interrupt(r: Regs, state: &'static State) {
 
         // TX
         if sr(r).read().txe() {
+            let ctrl = state.tx_ctrl.load(Ordering::Relaxed);
+            if ctrl != 0 {
+                // XON/XOFF go out ahead of the buffered data.
+                state.tx_ctrl.store(0, Ordering::Relaxed);
+                r.cr1().modify(|w| {
+                    w.set_txeie(true);
+                });
+                tdr(r).write_volatile(ctrl);
+                return;
+            }
+
+            if state.tx_paused.load(Ordering::Relaxed) {
+                // The receive interrupt that brings XON resumes the transmission.
+                r.cr1().modify(|w| {
+                    w.set_txeie(false);
+                });
+                return;
+            }
+
             let mut tx_reader = state.tx_buf.reader();
             let buf = tx_reader.pop_slice();
             if !buf.is_empty() {
@@ -124,6 +156,35 @@ unsafe fn on_interrupt(r: Regs, state: &'static State) {
     }
 }
 
+/// Count a byte pushed into the RX buffer and pause the remote above the high watermark.
+fn rx_pushed(state: &State) {
+    let level = state.rx_level.load(Ordering::Relaxed) + 1;
+    state.rx_level.store(level, Ordering::Relaxed);
+    if !state.rx_paused.load(Ordering::Relaxed)
+        && level >= state.rx_high_watermark.load(Ordering::Relaxed)
+    {
+        state.rx_paused.store(true, Ordering::Relaxed);
+        state.tx_ctrl.store(XOFF, Ordering::Relaxed);
+    }
+}
+
+/// XON: the receiver can take more data.
+pub const XON: u8 = 0x11;
+/// XOFF: the receiver asks the transmitter to pause.
+pub const XOFF: u8 = 0x13;
+
+/// Software (XON/XOFF) flow control configuration.
+///
+/// Used with [`BufferedUart::new_with_xonxoff`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct XonXoffConfig {
+    /// Send XOFF once this many bytes are waiting in the RX buffer.
+    pub high_watermark: usize,
+    /// Send XON once the RX buffer has drained down to this many bytes.
+    pub low_watermark: usize,
+}
+
 pub(super) struct State {
     rx_waker: AtomicWaker,
     rx_buf: RingBuffer,
@@ -131,6 +192,18 @@ pub(super) struct State {
     tx_buf: RingBuffer,
     tx_done: AtomicBool,
     tx_rx_refcount: AtomicU8,
+    /// XON/XOFF flow control is enabled
+    xonxoff: AtomicBool,
+    rx_high_watermark: AtomicUsize,
+    rx_low_watermark: AtomicUsize,
+    /// Bytes in `rx_buf`, only tracked with XON/XOFF
+    rx_level: AtomicUsize,
+    /// XOFF was sent and not yet followed by XON
+    rx_paused: AtomicBool,
+    /// XOFF was received and not yet followed by XON
+    tx_paused: AtomicBool,
+    /// XON or XOFF to send ahead of `tx_buf`, 0 if none
+    tx_ctrl: AtomicU8,
 }
 
 impl State {
@@ -142,6 +215,13 @@ impl State {
             tx_waker: AtomicWaker::new(),
             tx_done: AtomicBool::new(true),
             tx_rx_refcount: AtomicU8::new(0),
+            xonxoff: AtomicBool::new(false),
+            rx_high_watermark: AtomicUsize::new(0),
+            rx_low_watermark: AtomicUsize::new(0),
+            rx_level: AtomicUsize::new(0),
+            rx_paused: AtomicBool::new(false),
+            tx_paused: AtomicBool::new(false),
+            tx_ctrl: AtomicU8::new(0),
         }
     }
 }
@@ -223,6 +303,45 @@ impl<'d> BufferedUart<'d> {
             tx_buffer,
             rx_buffer,
             config,
+            None,
+        )
+    }
+
+    /// Create a new bidirectional buffered UART driver with XON/XOFF software flow control
+    ///
+    /// XOFF is sent when the RX buffer fills up to `xonxoff.high_watermark` and XON once it has
+    /// drained to `xonxoff.low_watermark`. Received XON/XOFF characters pause and resume the
+    /// transmission and are not stored in the RX buffer, so the link can't carry binary data.
+    ///
+    /// Returns [`ConfigError::XonXoffWatermarks`] unless `low_watermark < high_watermark <=
+    /// rx_buffer.len()`.
+    pub fn new_with_xonxoff<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        tx_buffer: &'d mut [u8],
+        rx_buffer: &'d mut [u8],
+        config: Config,
+        xonxoff: XonXoffConfig,
+    ) -> Result<Self, ConfigError> {
+        if xonxoff.low_watermark >= xonxoff.high_watermark
+            || xonxoff.high_watermark > rx_buffer.len()
+        {
+            return Err(ConfigError::XonXoffWatermarks);
+        }
+
+        Self::new_inner(
+            peri,
+            new_pin!(rx, AfType::input(config.rx_pull)),
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            None,
+            None,
+            None,
+            tx_buffer,
+            rx_buffer,
+            config,
+            Some(xonxoff),
         )
     }
 
@@ -248,6 +367,7 @@ impl<'d> BufferedUart<'d> {
             tx_buffer,
             rx_buffer,
             config,
+            None,
         )
     }
 
@@ -272,6 +392,7 @@ impl<'d> BufferedUart<'d> {
             tx_buffer,
             rx_buffer,
             config,
+            None,
         )
     }
 
@@ -296,6 +417,7 @@ impl<'d> BufferedUart<'d> {
             tx_buffer,
             rx_buffer,
             config,
+            None,
         )
     }
 
@@ -309,6 +431,7 @@ impl<'d> BufferedUart<'d> {
         tx_buffer: &'d mut [u8],
         rx_buffer: &'d mut [u8],
         config: Config,
+        xonxoff: Option<XonXoffConfig>,
     ) -> Result<Self, ConfigError> {
         let info = T::info();
         let state = T::buffered_state();
@@ -331,7 +454,7 @@ impl<'d> BufferedUart<'d> {
                 de,
             },
         };
-        this.enable_and_configure(tx_buffer, rx_buffer, &config)?;
+        this.enable_and_configure(tx_buffer, rx_buffer, &config, xonxoff)?;
         Ok(this)
     }
 
@@ -340,11 +463,26 @@ impl<'d> BufferedUart<'d> {
         tx_buffer: &'d mut [u8],
         rx_buffer: &'d mut [u8],
         config: &Config,
+        xonxoff: Option<XonXoffConfig>,
     ) -> Result<(), ConfigError> {
         let info = self.rx.info;
         let state = self.rx.state;
         state.tx_rx_refcount.store(2, Ordering::Relaxed);
 
+        if let Some(xonxoff) = xonxoff {
+            state
+                .rx_high_watermark
+                .store(xonxoff.high_watermark, Ordering::Relaxed);
+            state
+                .rx_low_watermark
+                .store(xonxoff.low_watermark, Ordering::Relaxed);
+        }
+        state.xonxoff.store(xonxoff.is_some(), Ordering::Relaxed);
+        state.rx_level.store(0, Ordering::Relaxed);
+        state.rx_paused.store(false, Ordering::Relaxed);
+        state.tx_paused.store(false, Ordering::Relaxed);
+        state.tx_ctrl.store(0, Ordering::Relaxed);
+
         info.rcc.enable_and_reset();
 
         let len = tx_buffer.len();
@@ -390,6 +528,12 @@ impl<'d> BufferedUart<'d> {
     pub fn send_break(&self) {
         self.tx.send_break()
     }
+
+    /// Enter Stop mode until a character arrives, see [`BufferedUartRx::enter_stop_until_rx`].
+    #[cfg(feature = "exti")]
+    pub fn enter_stop_until_rx(&mut self, exti: Peri<'_, impl exti::Channel>) {
+        self.rx.enter_stop_until_rx(exti)
+    }
 }
 
 impl<'d> BufferedUartRx<'d> {
@@ -405,8 +549,9 @@ impl<'d> BufferedUartRx<'d> {
 
                 let do_pend = state.rx_buf.is_full();
                 rx_reader.pop_done(len);
+                let resume = self.rx_popped(len);
 
-                if do_pend {
+                if do_pend || resume {
                     self.info.interrupt.pend();
                 }
 
@@ -431,8 +576,9 @@ impl<'d> BufferedUartRx<'d> {
 
                 let do_pend = state.rx_buf.is_full();
                 rx_reader.pop_done(len);
+                let resume = self.rx_popped(len);
 
-                if do_pend {
+                if do_pend || resume {
                     self.info.interrupt.pend();
                 }
 
@@ -462,11 +608,34 @@ impl<'d> BufferedUartRx<'d> {
         let mut rx_reader = unsafe { state.rx_buf.reader() };
         let full = state.rx_buf.is_full();
         rx_reader.pop_done(amt);
-        if full {
+        let resume = self.rx_popped(amt);
+        if full || resume {
             self.info.interrupt.pend();
         }
     }
 
+    /// Count `n` bytes taken out of the RX buffer. Returns true if XON has to be sent because the
+    /// buffer drained below the low watermark.
+    fn rx_popped(&self, n: usize) -> bool {
+        let state = self.state;
+        if !state.xonxoff.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        // The interrupt handler updates the level and the pause state too.
+        critical_section::with(|_| {
+            let level = state.rx_level.load(Ordering::Relaxed).saturating_sub(n);
+            state.rx_level.store(level, Ordering::Relaxed);
+            let resume = state.rx_paused.load(Ordering::Relaxed)
+                && level <= state.rx_low_watermark.load(Ordering::Relaxed);
+            if resume {
+                state.rx_paused.store(false, Ordering::Relaxed);
+                state.tx_ctrl.store(XON, Ordering::Relaxed);
+            }
+            resume
+        })
+    }
+
     /// we are ready to read if there is data in the buffer
     fn read_ready(&mut self) -> Result<bool, Error> {
         let state = self.state;
@@ -484,6 +653,15 @@ impl<'d> BufferedUartRx<'d> {
 
         Ok(())
     }
+
+    /// Enter Stop mode until the start bit of the next character arrives on RX.
+    ///
+    /// See [`UartRx::enter_stop_until_rx`](super::UartRx::enter_stop_until_rx). Bytes received
+    /// after wake-up go into the receive buffer as usual.
+    #[cfg(feature = "exti")]
+    pub fn enter_stop_until_rx(&mut self, exti: Peri<'_, impl exti::Channel>) {
+        super::low_power::stop_until_rx(self.rx.as_ref(), exti)
+    }
 }
 
 impl<'d> BufferedUartTx<'d> {
@@ -558,6 +736,13 @@ impl<'d> BufferedUartTx<'d> {
         }
     }
 
+    /// Returns true while transmission is paused by an XOFF from the remote.
+    ///
+    /// Always false without XON/XOFF flow control.
+    pub fn is_paused(&self) -> bool {
+        self.state.tx_paused.load(Ordering::Relaxed)
+    }
+
     /// Reconfigure the driver
     pub fn set_config(&mut self, config: &Config) -> Result<(), ConfigError> {
         reconfigure(self.info, self.kernel_clock, config)?;
@@ -0,0 +1,308 @@
+//! LIN (Local Interconnect Network) master and slave over USART
+//!
+//! The PY32 USART has no dedicated LIN mode, so the 13-bit break is generated by sending a
+//! break character (one frame of low bits) at 10/13 of the bus baud rate, and detected as a
+//! `0x00` character with a framing error. A LIN transceiver echoes everything written to the bus back on RX; the
+//! driver reads this echo back to detect bus collisions.
+
+use embassy_futures::join::join;
+use embassy_time::{with_timeout, Duration, Timer};
+
+use super::{rdr, sr, Config, ConfigError, DataBits, Error, Parity, StopBits, Uart};
+use crate::mode::Async;
+
+/// Maximum number of data bytes in a LIN frame.
+pub const MAX_DATA_LEN: usize = 8;
+
+/// Length of a LIN break, in bit times.
+const BREAK_BITS: u32 = 13;
+
+/// LIN checksum model
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChecksumModel {
+    /// LIN 1.x: the checksum covers the data bytes only.
+    Classic,
+    /// LIN 2.x: the checksum also covers the protected identifier.
+    ///
+    /// Diagnostic frames (0x3C and 0x3D) always use the classic checksum.
+    Enhanced,
+}
+
+/// LIN error
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinError {
+    /// Error reported by the underlying UART
+    Uart(Error),
+    /// No (complete) response within the LIN response time
+    Timeout,
+    /// The sync field was not 0x55
+    Sync,
+    /// The parity bits of the protected identifier are wrong
+    IdentifierParity,
+    /// The frame checksum does not match
+    Checksum,
+    /// The echo of a transmitted byte did not match, e.g. because of a bus collision
+    Readback,
+    /// The UART could not be configured for the break
+    Config(ConfigError),
+    /// The frame has no data bytes or more than [`MAX_DATA_LEN`]
+    DataLength,
+}
+
+impl From<Error> for LinError {
+    fn from(e: Error) -> Self {
+        Self::Uart(e)
+    }
+}
+
+impl From<ConfigError> for LinError {
+    fn from(e: ConfigError) -> Self {
+        Self::Config(e)
+    }
+}
+
+impl core::fmt::Display for LinError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uart(e) => write!(f, "{}", e),
+            Self::Timeout => write!(f, "Response timeout"),
+            Self::Sync => write!(f, "Invalid sync field"),
+            Self::IdentifierParity => write!(f, "Identifier parity error"),
+            Self::Checksum => write!(f, "Checksum error"),
+            Self::Readback => write!(f, "Readback mismatch"),
+            Self::Config(e) => write!(f, "{:?}", e),
+            Self::DataLength => write!(f, "Invalid data length"),
+        }
+    }
+}
+
+impl core::error::Error for LinError {}
+
+/// LIN driver
+///
+/// Works as a master (`send_header` followed by `respond` or `read_frame`) or as a slave
+/// (`read_header` followed by `respond` or `read_frame`). The UART is always used with
+/// 8 data bits, no parity and 1 stop bit.
+pub struct Lin<'d> {
+    uart: Uart<'d, Async>,
+    config: Config,
+}
+
+impl<'d> Lin<'d> {
+    /// Create a LIN driver on top of `uart`, using the baud rate from `config`.
+    pub fn new(mut uart: Uart<'d, Async>, mut config: Config) -> Result<Self, ConfigError> {
+        config.data_bits = DataBits::DataBits8;
+        config.parity = Parity::ParityNone;
+        config.stop_bits = StopBits::STOP1;
+        uart.tx.set_config(&config)?;
+        uart.rx.set_config(&config)?;
+        Ok(Self { uart, config })
+    }
+
+    /// Release the underlying UART.
+    pub fn free(self) -> Uart<'d, Async> {
+        self.uart
+    }
+
+    /// Compute the protected identifier (identifier and parity bits) of a 6-bit frame `id`.
+    pub fn protected_id(id: u8) -> u8 {
+        let id = id & 0x3F;
+        let bit = |n: u8| (id >> n) & 1;
+        let p0 = bit(0) ^ bit(1) ^ bit(2) ^ bit(4);
+        let p1 = !(bit(1) ^ bit(3) ^ bit(4) ^ bit(5)) & 1;
+        id | (p0 << 6) | (p1 << 7)
+    }
+
+    /// Compute the frame checksum of `data` sent with protected identifier `pid`.
+    pub fn checksum(pid: u8, data: &[u8], model: ChecksumModel) -> u8 {
+        let enhanced = model == ChecksumModel::Enhanced && !matches!(pid & 0x3F, 0x3C | 0x3D);
+        let init = if enhanced { pid as u16 } else { 0 };
+        let sum = data.iter().fold(init, |sum, &b| {
+            let sum = sum + b as u16;
+            if sum > 0xFF { sum - 0xFF } else { sum }
+        });
+        !(sum as u8)
+    }
+
+    /// Send a frame header (break, sync field and protected identifier) as the master.
+    pub async fn send_header(&mut self, id: u8) -> Result<(), LinError> {
+        self.send_lin_break().await?;
+
+        let header = [0x55, Self::protected_id(id)];
+        self.write_checked(&header).await
+    }
+
+    /// Wait for a frame header as a slave and return the frame identifier.
+    ///
+    /// Characters received outside of a header are ignored.
+    pub async fn read_header(&mut self) -> Result<u8, LinError> {
+        // A break is received as a 0x00 character without a valid stop bit.
+        loop {
+            let mut b = [0xFF; 1];
+            match self.uart.rx.read(&mut b).await {
+                Err(Error::Framing) => break,
+                Ok(()) if b[0] == 0x00 && sr(self.uart.rx.info.regs).read().fe() => break,
+                Ok(()) | Err(Error::Noise) | Err(Error::Overrun) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.clear_rx_flags();
+
+        // Sync and PID must be read in one go: each read discards a pending character.
+        let mut header = [0; 2];
+        let timeout = response_timeout(self.config.baudrate, header.len());
+        with_timeout(timeout, self.uart.rx.read(&mut header))
+            .await
+            .map_err(|_| LinError::Timeout)??;
+        if header[0] != 0x55 {
+            return Err(LinError::Sync);
+        }
+        let id = header[1] & 0x3F;
+        if Self::protected_id(id) != header[1] {
+            return Err(LinError::IdentifierParity);
+        }
+        Ok(id)
+    }
+
+    /// Send the response `data` and its checksum for frame `id`.
+    ///
+    /// A master calls this after [`send_header`][Self::send_header] to publish a frame, a
+    /// slave after [`read_header`][Self::read_header] returned an identifier it publishes.
+    pub async fn respond(
+        &mut self,
+        id: u8,
+        data: &[u8],
+        model: ChecksumModel,
+    ) -> Result<(), LinError> {
+        if data.is_empty() || data.len() > MAX_DATA_LEN {
+            return Err(LinError::DataLength);
+        }
+
+        let mut frame = [0; MAX_DATA_LEN + 1];
+        let n = data.len();
+        frame[..n].copy_from_slice(data);
+        frame[n] = Self::checksum(Self::protected_id(id), data, model);
+
+        self.write_checked(&frame[..n + 1]).await
+    }
+
+    /// Receive the response for frame `id` into `buf` and verify its checksum.
+    ///
+    /// `buf.len()` is the expected number of data bytes. Returns [`LinError::Timeout`] if the
+    /// response is not complete within the LIN maximum response time.
+    pub async fn read_frame(
+        &mut self,
+        id: u8,
+        buf: &mut [u8],
+        model: ChecksumModel,
+    ) -> Result<(), LinError> {
+        if buf.is_empty() || buf.len() > MAX_DATA_LEN {
+            return Err(LinError::DataLength);
+        }
+
+        let mut frame = [0; MAX_DATA_LEN + 1];
+        let n = buf.len();
+        let timeout = response_timeout(self.config.baudrate, n + 1);
+        with_timeout(timeout, self.uart.rx.read(&mut frame[..n + 1]))
+            .await
+            .map_err(|_| LinError::Timeout)??;
+
+        if Self::checksum(Self::protected_id(id), &frame[..n], model) != frame[n] {
+            return Err(LinError::Checksum);
+        }
+        buf.copy_from_slice(&frame[..n]);
+        Ok(())
+    }
+
+    /// Send a 13-bit break by sending a break character at 10/13 of the baud rate: with
+    /// 8 data bits, the USART holds the line low for 10 bit times.
+    async fn send_lin_break(&mut self) -> Result<(), LinError> {
+        let mut slow = self.config;
+        slow.baudrate = self.config.baudrate * 10 / BREAK_BITS;
+
+        self.uart.flush().await?;
+        self.uart.tx.set_config(&slow)?;
+        self.uart.tx.send_break();
+        // SBK is cleared by hardware during the stop bit of the break.
+        Timer::after(frame_time(slow.baudrate, 1)).await;
+        let r = self.uart.tx.info.regs;
+        while r.cr1().read().sbk() {}
+        self.uart.tx.set_config(&self.config)?;
+
+        // Drop the echo of the break.
+        self.clear_rx_flags();
+        Ok(())
+    }
+
+    /// Write `data` and check that the bus echoes it back unchanged.
+    async fn write_checked(&mut self, data: &[u8]) -> Result<(), LinError> {
+        let mut echo = [0; MAX_DATA_LEN + 1];
+        let echo = &mut echo[..data.len()];
+
+        let n = data.len();
+        let (rx, tx) = (&mut self.uart.rx, &mut self.uart.tx);
+        let (rx_res, tx_res) = join(
+            with_timeout(frame_time(self.config.baudrate, n) * 2, rx.read(echo)),
+            tx.write(data),
+        )
+        .await;
+        tx_res?;
+        rx_res.map_err(|_| LinError::Readback)??;
+
+        if echo != data {
+            return Err(LinError::Readback);
+        }
+        Ok(())
+    }
+
+    /// Discard a pending character and clear the error flags.
+    fn clear_rx_flags(&mut self) {
+        let r = self.uart.rx.info.regs;
+        // Reading SR then DR clears RXNE, IDLE and the error flags.
+        let _ = sr(r).read();
+        unsafe { rdr(r).read_volatile() };
+    }
+}
+
+/// LIN maximum response time for `n` characters: the nominal time plus 40%.
+fn response_timeout(baudrate: u32, n: usize) -> Duration {
+    frame_time(baudrate, n) * 14 / 10
+}
+
+/// Nominal time to transfer `n` characters of 10 bits each.
+fn frame_time(baudrate: u32, n: usize) -> Duration {
+    Duration::from_micros(10 * n as u64 * 1_000_000 / baudrate as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protected_id_parity() {
+        assert_eq!(Lin::protected_id(0x00), 0x80);
+        assert_eq!(Lin::protected_id(0x01), 0xC1);
+        assert_eq!(Lin::protected_id(0x10), 0x50);
+        assert_eq!(Lin::protected_id(0x3C), 0x3C);
+        assert_eq!(Lin::protected_id(0x3D), 0x7D);
+        // Only the low 6 bits are the identifier.
+        assert_eq!(Lin::protected_id(0xFF), Lin::protected_id(0x3F));
+    }
+
+    #[test]
+    fn checksum_models() {
+        let data = [0x55, 0x93, 0xE5];
+        // LIN 2.x specification example
+        assert_eq!(Lin::checksum(0x4A, &data, ChecksumModel::Enhanced), 0xE6);
+        assert_eq!(Lin::checksum(0x4A, &data, ChecksumModel::Classic), 0x31);
+        // Diagnostic frames always use the classic checksum.
+        assert_eq!(Lin::checksum(0x3C, &data, ChecksumModel::Enhanced), 0x31);
+        // The carry is added back in.
+        assert_eq!(
+            Lin::checksum(0, &[0xFF, 0xFF], ChecksumModel::Classic),
+            0x00
+        );
+    }
+}
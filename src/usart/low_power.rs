@@ -0,0 +1,46 @@
+//! Stop mode with wake-up on UART reception
+//!
+//! The USART has no wake-up logic of its own and stops with its kernel clock in Stop mode.
+//! Instead the RX pin is routed to EXTI, so the falling edge of a start bit wakes the chip and
+//! the system clock is restored. That start bit arrives while the chip still runs from HSI, so
+//! the character it begins is lost; the USART receives from the next one on. Timers, including
+//! the time driver, do not count while the chip is stopped.
+
+use embassy_hal_internal::Peri;
+
+use crate::exti;
+use crate::gpio::{AnyPin, SealedPin as _};
+use crate::pac::RCC;
+
+/// Enter Stop mode until a falling edge on `rx`, then restore the system clock.
+///
+/// Other interrupts wake the chip too; they are serviced and the chip is stopped again.
+pub(super) fn stop_until_rx(rx: Option<&Peri<'_, AnyPin>>, exti: Peri<'_, impl exti::Channel>) {
+    let rx = unwrap!(rx, "Stop mode wake-up needs an RX pin");
+    let line = rx.pin();
+    assert_eq!(line, exti.number());
+
+    let (cr, cfgr) = (RCC.cr().read(), RCC.cfgr().read());
+    // Safety: only SLEEPDEEP is changed, and it is cleared again before returning.
+    let mut scb = unsafe { cortex_m::Peripherals::steal() }.SCB;
+
+    exti::arm(line, rx.port(), false, true);
+    scb.set_sleepdeep();
+    loop {
+        // With interrupts masked, the EXTI interrupt cannot fire between the check and WFI.
+        // A pending interrupt still ends WFI, and is serviced once the clock is back.
+        cortex_m::interrupt::disable();
+        let waiting = exti::is_armed(line);
+        if waiting {
+            cortex_m::asm::dsb();
+            cortex_m::asm::wfi();
+            crate::rcc::reinit_after_stop(cr, cfgr);
+        }
+        unsafe { cortex_m::interrupt::enable() };
+
+        if !waiting {
+            break;
+        }
+    }
+    scb.clear_sleepdeep();
+}
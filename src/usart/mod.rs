@@ -8,11 +8,12 @@
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
-use core::sync::atomic::{compiler_fence, AtomicU8, Ordering};
+#[cfg(not(dma))]
+use core::sync::atomic::{AtomicPtr, AtomicUsize};
+use core::sync::atomic::{compiler_fence, AtomicBool, AtomicU32, AtomicU8, Ordering};
 use core::task::Poll;
 
 use embassy_embedded_hal::SetConfig;
-#[cfg(dma)]
 use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{Peri, PeripheralType};
 use embassy_sync::waitqueue::AtomicWaker;
@@ -24,14 +25,16 @@ use crate::pac::usart::regs::Sr;
 use crate::pac::usart::Usart as Regs;
 use crate::pac::usart::{regs, vals};
 
+#[cfg(dma)]
+use crate::dma::word::Word;
 #[cfg(dma)]
 use crate::dma::ChannelAndRequest;
-use crate::gpio::{self, AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
+#[cfg(feature = "exti")]
+use crate::exti;
+use crate::gpio::{self, AfType, AnyPin, Flex, OutputType, Pull, SealedPin as _, Speed};
 use crate::interrupt::typelevel::Interrupt as _;
 use crate::interrupt::{self, Interrupt, InterruptExt};
-#[cfg(dma)]
-use crate::mode::Async;
-use crate::mode::{Blocking, Mode};
+use crate::mode::{Async, Blocking, Mode};
 use crate::rcc::{RccInfo, SealedRccPeripheral};
 use crate::time::Hertz;
 
@@ -46,7 +49,11 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
     }}
 }
 
+#[cfg(dma)]
 unsafe fn on_interrupt(r: Regs, s: &'static State) {
+    // The ring-buffered transmitter follows its DMA channel and handles its own TC.
+    s.tx_ring.on_interrupt(r, &s.de);
+
     let (sr, cr1, cr3) = (sr(r).read(), r.cr1().read(), r.cr3().read());
 
     let has_errors = (sr.pe() && cr1.peie()) || ((sr.fe() || sr.ne() || sr.ore()) && cr3.eie());
@@ -78,6 +85,12 @@ unsafe fn on_interrupt(r: Regs, s: &'static State) {
             // disable Transmission complete interrupt
             w.set_tcie(false);
         });
+    } else if cr1.rxneie() && !cr3.dmar() && (sr.rxne() || sr.abre()) {
+        // Character received without DMA, e.g. the auto baud rate detection character, or
+        // the detection failed
+        r.cr1().modify(|w| {
+            w.set_rxneie(false);
+        });
     } else if cr1.rxneie() {
         // We cannot check the RXNE flag as it is auto-cleared by the DMA controller
 
@@ -90,6 +103,66 @@ unsafe fn on_interrupt(r: Regs, s: &'static State) {
     s.rx_waker.wake();
 }
 
+// Without DMA, the async driver moves every byte in the interrupt handler.
+#[cfg(not(dma))]
+unsafe fn on_interrupt(r: Regs, s: &'static State) {
+    let (sr, cr1, cr3) = (sr(r).read(), r.cr1().read(), r.cr3().read());
+
+    if cr1.txeie() && sr.txe() {
+        match s.tx.next() {
+            Some(p) if s.tx.wide() => unsafe { tdr_u16(r).write_volatile(*(p as *const u16)) },
+            Some(p) => unsafe { tdr(r).write_volatile(*p) },
+            None => {
+                // Last byte is in the shift register, the buffer can be released.
+                r.cr1().modify(|w| w.set_txeie(false));
+                s.tx_waker.wake();
+            }
+        }
+    }
+
+    if cr1.tcie() && sr.tc() {
+        // Transmission complete detected
+        r.cr1().modify(|w| w.set_tcie(false));
+        s.tx_waker.wake();
+    }
+
+    if cr1.rxneie() {
+        let has_errors =
+            (sr.pe() && cr1.peie()) || ((sr.fe() || sr.ne() || sr.ore()) && cr3.eie());
+        let idle = cr1.idleie() && sr.idle();
+        if !(sr.rxne() || has_errors || idle || sr.abre()) {
+            return;
+        }
+
+        // On v1, reading DR after SR clears RXNE, IDLE and the error flags.
+        let w = unsafe { rdr_u16(r).read_volatile() };
+        if sr.rxne() && !has_errors {
+            match s.rx.next() {
+                Some(slot) if s.rx.wide() => unsafe { *(slot as *mut u16) = w },
+                Some(slot) => unsafe { *slot = w as u8 },
+                None => {}
+            }
+        }
+
+        // IDLE left over from before the read started is cleared above and only set again
+        // after new data, so an idle line without received bytes is not a completion.
+        let done =
+            has_errors || sr.abre() || s.rx.remaining() == 0 || (idle && s.rx.transferred() > 0);
+        if done {
+            s.rx_sr.store(sr.0, Ordering::Relaxed);
+            r.cr1().modify(|w| {
+                w.set_rxneie(false);
+                w.set_peie(false);
+                w.set_idleie(false);
+            });
+            r.cr3().modify(|w| w.set_eie(false));
+
+            compiler_fence(Ordering::SeqCst);
+            s.rx_waker.wake();
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// Number of data bits
@@ -97,6 +170,9 @@ pub enum DataBits {
     /// 8 Data Bits
     DataBits8,
     /// 9 Data Bits
+    ///
+    /// The ninth bit is only transferred by the `_u16` read and write methods, such as
+    /// [`UartTx::write_u16`] and [`UartRx::read_u16`]. Not supported together with parity.
     DataBits9,
 }
 
@@ -126,6 +202,45 @@ pub enum StopBits {
     // STOP1P5,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+/// Automatic baud rate detection mode
+///
+/// The baud rate is measured on the first character received after the USART is enabled.
+pub enum AutoBaudMode {
+    /// Measure the start bit; the character must start with a `1` bit (LSB first)
+    StartBit,
+    /// Measure from falling edge to falling edge; the character must start with `10`
+    FallingEdge,
+    /// The character must be 0x7F
+    Frame7F,
+    /// The character must be 0x55
+    Frame55,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+/// RS-485 driver enable (DE) polarity
+pub enum DePolarity {
+    /// DE is high while transmitting
+    ActiveHigh,
+    /// DE is low while transmitting
+    ActiveLow,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+/// Receiver wakeup method from mute mode
+pub enum WakeupMethod {
+    /// Wake up when the line has been idle for one frame
+    IdleLine,
+    /// Wake up on an address character matching [`Config::address`]
+    ///
+    /// An address character has its most significant bit set (bit 8 with 9 data bits,
+    /// bit 7 with 8 data bits).
+    AddressMark,
+}
+
 #[non_exhaustive]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -137,6 +252,13 @@ pub enum ConfigError {
     BaudrateTooHigh,
     /// Rx or Tx not enabled
     RxOrTxNotEnabled,
+    /// The combination of data bits and parity is not supported
+    DataParityNotSupported,
+    /// The XON/XOFF low watermark is not below the high watermark, or the high watermark is
+    /// larger than the RX buffer
+    XonXoffWatermarks,
+    /// The Modbus slave address is not in 1..=247
+    ModbusAddress,
 }
 
 #[non_exhaustive]
@@ -161,6 +283,23 @@ pub struct Config {
     /// Set the pull configuration for the RX pin.
     pub rx_pull: Pull,
 
+    /// Measure the baud rate on the first received character instead of using `baudrate`.
+    ///
+    /// `baudrate` is still used until the detection completes. See [`UartRx::detect_baudrate`].
+    pub auto_baud: Option<AutoBaudMode>,
+
+    /// Polarity of the RS-485 driver enable (DE) pin, if one is used.
+    pub de_polarity: DePolarity,
+    /// Time from asserting DE to the start bit, in 1/16 bit times.
+    pub de_assertion_time: u8,
+    /// Time from the end of the last stop bit to releasing DE, in 1/16 bit times.
+    pub de_deassertion_time: u8,
+
+    /// How the receiver leaves mute mode, see [`UartRx::enter_mute_mode`].
+    pub wakeup_method: WakeupMethod,
+    /// Own node address (4 bits) for [`WakeupMethod::AddressMark`].
+    pub address: u8,
+
     // private: set by new_half_duplex, not by the user.
     half_duplex: bool,
 }
@@ -173,6 +312,17 @@ impl Config {
     fn rx_af(&self) -> AfType {
         AfType::input(self.rx_pull)
     }
+
+    /// Put `pin` in output mode as a released DE pin.
+    fn de_pin<'d>(&self, pin: Peri<'d, impl gpio::Pin>) -> Option<Flex<'d>> {
+        let mut pin = Flex::new(pin);
+        match self.de_polarity {
+            DePolarity::ActiveHigh => pin.set_low(),
+            DePolarity::ActiveLow => pin.set_high(),
+        }
+        pin.set_as_output(Speed::Medium);
+        Some(pin)
+    }
 }
 
 impl Default for Config {
@@ -185,6 +335,12 @@ impl Default for Config {
             // historical behavior
             detect_previous_overrun: false,
             rx_pull: Pull::None,
+            auto_baud: None,
+            de_polarity: DePolarity::ActiveHigh,
+            de_assertion_time: 0,
+            de_deassertion_time: 0,
+            wakeup_method: WakeupMethod::IdleLine,
+            address: 0,
             half_duplex: false,
         }
     }
@@ -231,6 +387,8 @@ pub enum Error {
     Parity,
     /// Buffer too large for DMA
     BufferTooLong,
+    /// Automatic baud rate detection failed, or is not enabled
+    BaudrateDetection,
 }
 
 impl core::fmt::Display for Error {
@@ -241,6 +399,7 @@ impl core::fmt::Display for Error {
             Error::Overrun => write!(f, "rx buffer overrun"),
             Error::Parity => write!(f, "parity check error"),
             Error::BufferTooLong => write!(f, "buffer too large for DMA"),
+            Error::BaudrateDetection => write!(f, "baud rate detection failed"),
         }
     }
 }
@@ -288,7 +447,7 @@ pub struct UartTx<'d, M: Mode> {
     kernel_clock: Hertz,
     tx: Option<Peri<'d, AnyPin>>,
     cts: Option<Peri<'d, AnyPin>>,
-    de: Option<Peri<'d, AnyPin>>,
+    de: Option<Flex<'d>>,
     #[cfg(dma)]
     tx_dma: Option<ChannelAndRequest<'d>>,
     _phantom: PhantomData<M>,
@@ -368,6 +527,7 @@ impl<'d> UartTx<'d, Async> {
             peri,
             new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
             None,
+            None,
             new_dma!(tx_dma),
             config,
         )
@@ -385,13 +545,48 @@ impl<'d> UartTx<'d, Async> {
             peri,
             new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
             new_pin!(cts, AfType::input(Pull::None)),
+            None,
             new_dma!(tx_dma),
             config,
         )
     }
 
+    /// Create a new tx-only UART driving an RS-485 transceiver's driver enable (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    pub fn new_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        tx: Peri<'d, impl TxPin<T>>,
+        de: Peri<'d, impl DePin<T>>,
+        tx_dma: Peri<'d, impl TxDma<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let this = Self::new_inner(
+            peri,
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            None,
+            config.de_pin(de),
+            new_dma!(tx_dma),
+            config,
+        )?;
+        this.info.interrupt.unpend();
+        unsafe { this.info.interrupt.enable() };
+        Ok(this)
+    }
+
     /// Initiate an asynchronous UART write
     pub async fn write(&mut self, buffer: &[u8]) -> Result<(), Error> {
+        self.inner_write(buffer, tdr(self.info.regs)).await
+    }
+
+    /// Initiate an asynchronous UART write of 9-bit words, see [`DataBits::DataBits9`]
+    pub async fn write_u16(&mut self, buffer: &[u16]) -> Result<(), Error> {
+        self.inner_write(buffer, tdr_u16(self.info.regs)).await
+    }
+
+    async fn inner_write<W: Word>(&mut self, buffer: &[W], tdr: *mut W) -> Result<(), Error> {
         let r = self.info.regs;
 
         // Enable Transmitter and disable Receiver for Half-Duplex mode
@@ -402,21 +597,168 @@ impl<'d> UartTx<'d, Async> {
             r.cr1().write_value(cr1);
         }
 
+        let s = self.state;
+        s.de.assert();
+        // Don't leave the bus driven if this future is dropped
+        let de_release = OnDrop::new(move || s.de.release());
+
         let ch = self.tx_dma.as_mut().unwrap();
+        // DMA writes don't clear TC, a stale flag would end the wait for completion early
+        sr(r).modify(|w| w.set_tc(false));
         r.cr3().modify(|reg| {
             reg.set_dmat(true);
         });
         // If we don't assign future to a variable, the data register pointer
         // is held across an await and makes the future non-Send.
-        let transfer = unsafe { ch.write(buffer, tdr(r), Default::default()) };
+        let transfer = unsafe { ch.write(buffer, tdr, Default::default()) };
         transfer.await;
+
+        self.de_release().await?;
+        de_release.defuse();
+        Ok(())
+    }
+}
+
+#[cfg(not(dma))]
+impl<'d> UartTx<'d, Async> {
+    /// Useful if you only want Uart Tx. It saves 1 pin and consumes a little less power.
+    ///
+    /// Bytes are moved by the USART interrupt, for chips without DMA.
+    pub fn new<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        tx: Peri<'d, impl TxPin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let this = Self::new_inner(
+            peri,
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            None,
+            None,
+            config,
+        )?;
+        this.info.interrupt.unpend();
+        unsafe { this.info.interrupt.enable() };
+        Ok(this)
+    }
+
+    /// Create a new tx-only UART with a clear-to-send pin
+    pub fn new_with_cts<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        tx: Peri<'d, impl TxPin<T>>,
+        cts: Peri<'d, impl CtsPin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let this = Self::new_inner(
+            peri,
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(cts, AfType::input(Pull::None)),
+            None,
+            config,
+        )?;
+        this.info.interrupt.unpend();
+        unsafe { this.info.interrupt.enable() };
+        Ok(this)
+    }
+
+    /// Create a new tx-only UART driving an RS-485 transceiver's driver enable (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    pub fn new_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        tx: Peri<'d, impl TxPin<T>>,
+        de: Peri<'d, impl DePin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let this = Self::new_inner(
+            peri,
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            None,
+            config.de_pin(de),
+            config,
+        )?;
+        this.info.interrupt.unpend();
+        unsafe { this.info.interrupt.enable() };
+        Ok(this)
+    }
+
+    /// Initiate an asynchronous UART write
+    pub async fn write(&mut self, buffer: &[u8]) -> Result<(), Error> {
+        self.inner_write(buffer.as_ptr(), buffer.len(), false).await
+    }
+
+    /// Initiate an asynchronous UART write of 9-bit words, see [`DataBits::DataBits9`]
+    pub async fn write_u16(&mut self, buffer: &[u16]) -> Result<(), Error> {
+        self.inner_write(buffer.as_ptr() as *const u8, buffer.len(), true)
+            .await
+    }
+
+    /// Write `len` bytes, or `u16` words if `wide`, from `ptr`, which must stay valid until
+    /// this future completes or is dropped.
+    async fn inner_write(&mut self, ptr: *const u8, len: usize, wide: bool) -> Result<(), Error> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        let r = self.info.regs;
+        let s = self.state;
+
+        // Enable Transmitter and disable Receiver for Half-Duplex mode
+        let mut cr1 = r.cr1().read();
+        if r.cr3().read().hdsel() && !cr1.te() {
+            cr1.set_te(true);
+            cr1.set_re(false);
+            r.cr1().write_value(cr1);
+        }
+
+        s.de.assert();
+        // Don't leave the bus driven if this future is dropped
+        let de_release = OnDrop::new(move || s.de.release());
+
+        // Stop the interrupt from touching the buffer if this future is dropped
+        let on_drop = OnDrop::new(move || {
+            critical_section::with(|_| r.cr1().modify(|w| w.set_txeie(false)));
+            s.tx.stop();
+        });
+
+        // The interrupt handler only reads from the buffer.
+        s.tx.start(ptr as *mut u8, len, wide);
+        // CR1 is shared with the receive side, which the interrupt handler also modifies.
+        critical_section::with(|_| r.cr1().modify(|w| w.set_txeie(true)));
+
+        poll_fn(|cx| {
+            s.tx_waker.register(cx.waker());
+            if r.cr1().read().txeie() {
+                return Poll::Pending;
+            }
+            Poll::Ready(())
+        })
+        .await;
+
+        drop(on_drop);
+        self.de_release().await?;
+        de_release.defuse();
         Ok(())
     }
+}
 
+impl<'d> UartTx<'d, Async> {
     /// Wait until transmission complete
     pub async fn flush(&mut self) -> Result<(), Error> {
         flush(&self.info, &self.state).await
     }
+
+    /// Wait for the transmission to complete, then release DE. Does nothing without a DE pin.
+    async fn de_release(&self) -> Result<(), Error> {
+        if self.de.is_some() {
+            flush(self.info, self.state).await?;
+            self.state.de.release();
+        }
+        Ok(())
+    }
 }
 
 impl<'d> UartTx<'d, Blocking> {
@@ -432,6 +774,7 @@ impl<'d> UartTx<'d, Blocking> {
             peri,
             new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
             None,
+            None,
             #[cfg(dma)]
             None,
             config,
@@ -449,6 +792,28 @@ impl<'d> UartTx<'d, Blocking> {
             peri,
             new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
             new_pin!(cts, AfType::input(config.rx_pull)),
+            None,
+            #[cfg(dma)]
+            None,
+            config,
+        )
+    }
+
+    /// Create a new blocking tx-only UART driving an RS-485 transceiver's driver enable (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    pub fn new_blocking_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        tx: Peri<'d, impl TxPin<T>>,
+        de: Peri<'d, impl DePin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(tx, AfType::output(OutputType::PushPull, Speed::Medium)),
+            None,
+            config.de_pin(de),
             #[cfg(dma)]
             None,
             config,
@@ -461,6 +826,7 @@ impl<'d, M: Mode> UartTx<'d, M> {
         _peri: Peri<'d, T>,
         tx: Option<Peri<'d, AnyPin>>,
         cts: Option<Peri<'d, AnyPin>>,
+        de: Option<Flex<'d>>,
         #[cfg(dma)] tx_dma: Option<ChannelAndRequest<'d>>,
         config: Config,
     ) -> Result<Self, ConfigError> {
@@ -470,7 +836,7 @@ impl<'d, M: Mode> UartTx<'d, M> {
             kernel_clock: T::frequency(),
             tx,
             cts,
-            de: None,
+            de,
             #[cfg(dma)]
             tx_dma,
             _phantom: PhantomData,
@@ -490,17 +856,37 @@ impl<'d, M: Mode> UartTx<'d, M> {
             w.set_ctse(self.cts.is_some());
         });
         configure(info, self.kernel_clock, config, false, true)?;
+        if let Some(de) = &self.de {
+            state.de.set(de, config);
+        }
 
         Ok(())
     }
 
     /// Reconfigure the driver
     pub fn set_config(&mut self, config: &Config) -> Result<(), ConfigError> {
-        reconfigure(self.info, self.kernel_clock, config)
+        reconfigure(self.info, self.kernel_clock, config)?;
+        if let Some(de) = &self.de {
+            self.state.de.set(de, config);
+        }
+        Ok(())
     }
 
     /// Perform a blocking UART write
     pub fn blocking_write(&mut self, buffer: &[u8]) -> Result<(), Error> {
+        self.blocking_write_inner(buffer, |r, b| unsafe { tdr(r).write_volatile(b) })
+    }
+
+    /// Perform a blocking UART write of 9-bit words, see [`DataBits::DataBits9`]
+    pub fn blocking_write_u16(&mut self, buffer: &[u16]) -> Result<(), Error> {
+        self.blocking_write_inner(buffer, |r, w| unsafe { tdr_u16(r).write_volatile(w) })
+    }
+
+    fn blocking_write_inner<W: Copy>(
+        &mut self,
+        buffer: &[W],
+        write: impl Fn(Regs, W),
+    ) -> Result<(), Error> {
         let r = self.info.regs;
 
         // Enable Transmitter and disable Receiver for Half-Duplex mode
@@ -511,9 +897,14 @@ impl<'d, M: Mode> UartTx<'d, M> {
             r.cr1().write_value(cr1);
         }
 
+        self.state.de.assert();
         for &b in buffer {
             while !sr(r).read().txe() {}
-            unsafe { tdr(r).write_volatile(b) };
+            write(r, b);
+        }
+        if self.de.is_some() {
+            blocking_flush(self.info)?;
+            self.state.de.release();
         }
         Ok(())
     }
@@ -534,16 +925,23 @@ impl<'d, M: Mode> UartTx<'d, M> {
 async fn flush(info: &Info, state: &State) -> Result<(), Error> {
     let r = info.regs;
     if r.cr1().read().te() && !sr(r).read().tc() {
-        r.cr1().modify(|w| {
-            // enable Transmission Complete interrupt
-            w.set_tcie(true);
+        // CR1 is shared with the receive side, which the interrupt handler also modifies.
+        critical_section::with(|_| {
+            r.cr1().modify(|w| {
+                // enable Transmission Complete interrupt
+                w.set_tcie(true);
+            })
         });
 
         compiler_fence(Ordering::SeqCst);
 
         // future which completes when Transmission complete is detected
         let abort = poll_fn(move |cx| {
+            #[cfg(dma)]
             state.rx_waker.register(cx.waker());
+            // Without DMA, a read pending on the other half of a split UART owns `rx_waker`.
+            #[cfg(not(dma))]
+            state.tx_waker.register(cx.waker());
 
             let sr = sr(r).read();
             if sr.tc() {
@@ -617,21 +1015,10 @@ impl<'d> UartRx<'d, Async> {
         )
     }
 
-    /// Initiate an asynchronous UART read
-    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
-        self.inner_read(buffer, false).await?;
-
-        Ok(())
-    }
-
-    /// Initiate an asynchronous read with idle line detection enabled
-    pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
-        self.inner_read(buffer, true).await
-    }
-
-    async fn inner_read_run(
+    async fn inner_read_run<W: Word>(
         &mut self,
-        buffer: &mut [u8],
+        buffer: &mut [W],
+        rdr_w: *mut W,
         enable_idle_line_detection: bool,
     ) -> Result<ReadCompletionEvent, Error> {
         let r = self.info.regs;
@@ -675,7 +1062,7 @@ impl<'d> UartRx<'d, Async> {
         // Start USART DMA
         // will not do anything yet because DMAR is not yet set
         // future which will complete when DMA Read request completes
-        let transfer = unsafe { ch.read(rdr(r), buffer, Default::default()) };
+        let transfer = unsafe { ch.read(rdr_w, buffer, Default::default()) };
 
         // clear ORE flag just before enabling DMA Rx Request: can be mandatory for the second transfer
         if !self.detect_previous_overrun {
@@ -821,6 +1208,27 @@ impl<'d> UartRx<'d, Async> {
         &mut self,
         buffer: &mut [u8],
         enable_idle_line_detection: bool,
+    ) -> Result<usize, Error> {
+        let rdr = rdr(self.info.regs);
+        self.inner_read_words(buffer, rdr, enable_idle_line_detection)
+            .await
+    }
+
+    async fn inner_read_u16(
+        &mut self,
+        buffer: &mut [u16],
+        enable_idle_line_detection: bool,
+    ) -> Result<usize, Error> {
+        let rdr = rdr_u16(self.info.regs);
+        self.inner_read_words(buffer, rdr, enable_idle_line_detection)
+            .await
+    }
+
+    async fn inner_read_words<W: Word>(
+        &mut self,
+        buffer: &mut [W],
+        rdr_w: *mut W,
+        enable_idle_line_detection: bool,
     ) -> Result<usize, Error> {
         if buffer.is_empty() {
             return Ok(0);
@@ -832,14 +1240,252 @@ impl<'d> UartRx<'d, Async> {
 
         // wait for DMA to complete or IDLE line detection if requested
         let res = self
-            .inner_read_run(buffer, enable_idle_line_detection)
+            .inner_read_run(buffer, rdr_w, enable_idle_line_detection)
+            .await;
+
+        match res {
+            Ok(ReadCompletionEvent::DmaCompleted) => Ok(buffer_len),
+            Ok(ReadCompletionEvent::Idle(n)) => Ok(n),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(not(dma))]
+impl<'d> UartRx<'d, Async> {
+    /// Create a new rx-only UART with no hardware flow control.
+    ///
+    /// Useful if you only want Uart Rx. It saves 1 pin and consumes a little less power.
+    /// Bytes are moved by the USART interrupt, for chips without DMA.
+    pub fn new<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        rx: Peri<'d, impl RxPin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(peri, new_pin!(rx, AfType::input(config.rx_pull)), None, config)
+    }
+
+    /// Create a new rx-only UART with a request-to-send pin
+    pub fn new_with_rts<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        rx: Peri<'d, impl RxPin<T>>,
+        rts: Peri<'d, impl RtsPin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, AfType::input(config.rx_pull)),
+            new_pin!(rts, AfType::output(OutputType::PushPull, Speed::Medium)),
+            config,
+        )
+    }
+
+    async fn inner_read(
+        &mut self,
+        buffer: &mut [u8],
+        enable_idle_line_detection: bool,
+    ) -> Result<usize, Error> {
+        self.inner_read_raw(
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            false,
+            enable_idle_line_detection,
+        )
+        .await
+    }
+
+    async fn inner_read_u16(
+        &mut self,
+        buffer: &mut [u16],
+        enable_idle_line_detection: bool,
+    ) -> Result<usize, Error> {
+        self.inner_read_raw(
+            buffer.as_mut_ptr() as *mut u8,
+            buffer.len(),
+            true,
+            enable_idle_line_detection,
+        )
+        .await
+    }
+
+    /// Read up to `len` bytes, or `u16` words if `wide`, into `ptr`, which must stay valid
+    /// until this future completes or is dropped.
+    async fn inner_read_raw(
+        &mut self,
+        ptr: *mut u8,
+        len: usize,
+        wide: bool,
+        enable_idle_line_detection: bool,
+    ) -> Result<usize, Error> {
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let r = self.info.regs;
+        let s = self.state;
+
+        // Call flush for Half-Duplex mode if some bytes were written and flush was not called.
+        // It prevents reading of bytes which have just been written.
+        if r.cr3().read().hdsel() && r.cr1().read().te() {
+            flush(&self.info, &self.state).await?;
+
+            // Disable Transmitter and enable Receiver after flush
+            r.cr1().modify(|reg| {
+                reg.set_re(true);
+                reg.set_te(false);
+            });
+        }
+
+        // make sure USART state is restored to neutral state when this future is dropped
+        let on_drop = OnDrop::new(move || {
+            critical_section::with(|_| {
+                r.cr1().modify(|w| {
+                    w.set_rxneie(false);
+                    w.set_peie(false);
+                    w.set_idleie(false);
+                });
+                r.cr3().modify(|w| w.set_eie(false));
+            });
+            s.rx.stop();
+        });
+
+        // clear ORE flag before enabling reception: can be mandatory for the second transfer
+        if !self.detect_previous_overrun {
+            let sr = sr(r).read();
+            // This read also clears the error and idle interrupt flags on v1.
+            unsafe { rdr(r).read_volatile() };
+            clear_interrupt_flags(r, sr);
+        }
+
+        s.rx_sr.store(0, Ordering::Relaxed);
+        s.rx.start(ptr, len, wide);
+
+        // CR1 is shared with the transmit side, which the interrupt handler also modifies.
+        critical_section::with(|_| {
+            r.cr1().modify(|w| {
+                w.set_rxneie(true);
+                // enable parity interrupt if not ParityNone
+                w.set_peie(w.pce());
+                w.set_idleie(enable_idle_line_detection);
+            });
+            // enable Error Interrupt: (Frame error, Noise error, Overrun error)
+            r.cr3().modify(|w| w.set_eie(true));
+        });
+
+        // future which completes when the buffer is full, or on idle line or error
+        let res = poll_fn(|cx| {
+            s.rx_waker.register(cx.waker());
+
+            if r.cr1().read().rxneie() {
+                return Poll::Pending;
+            }
+
+            compiler_fence(Ordering::SeqCst);
+
+            let sr = regs::Sr(s.rx_sr.load(Ordering::Relaxed));
+            if sr.pe() {
+                return Poll::Ready(Err(Error::Parity));
+            }
+            if sr.fe() {
+                return Poll::Ready(Err(Error::Framing));
+            }
+            if sr.ne() {
+                return Poll::Ready(Err(Error::Noise));
+            }
+            if sr.ore() {
+                return Poll::Ready(Err(Error::Overrun));
+            }
+
+            Poll::Ready(Ok(s.rx.transferred()))
+        })
+        .await;
+
+        drop(on_drop);
+
+        res
+    }
+}
+
+impl<'d> UartRx<'d, Async> {
+    /// Initiate an asynchronous UART read
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.inner_read(buffer, false).await?;
+
+        Ok(())
+    }
+
+    /// Wait for automatic baud rate detection to complete and return the measured baud rate.
+    ///
+    /// Requires [`Config::auto_baud`]. The character used for the measurement is consumed.
+    /// Returns immediately if the detection already completed; reapply the config with
+    /// [`set_config`][Self::set_config] to measure again.
+    pub async fn detect_baudrate(&mut self) -> Result<u32, Error> {
+        let r = self.info.regs;
+        if !r.cr3().read().abren() {
+            return Err(Error::BaudrateDetection);
+        }
+
+        if !sr(r).read().abrf() {
+            let on_drop = OnDrop::new(move || {
+                critical_section::with(|_| {
+                    r.cr1().modify(|w| w.set_rxneie(false));
+                    r.cr3().modify(|w| w.set_eie(false));
+                })
+            });
+
+            // The interrupt handler disables RXNEIE once the character is received, or once
+            // the detection fails. ABRE has no interrupt of its own, a failed measurement
+            // also shows up as a framing error, which is why EIE is enabled as well.
+            critical_section::with(|_| {
+                r.cr1().modify(|w| w.set_rxneie(true));
+                r.cr3().modify(|w| w.set_eie(true));
+            });
+
+            let s = self.state;
+            poll_fn(|cx| {
+                s.rx_waker.register(cx.waker());
+                if r.cr1().read().rxneie() {
+                    return Poll::Pending;
+                }
+                Poll::Ready(())
+            })
             .await;
 
-        match res {
-            Ok(ReadCompletionEvent::DmaCompleted) => Ok(buffer_len),
-            Ok(ReadCompletionEvent::Idle(n)) => Ok(n),
-            Err(e) => Err(e),
-        }
+            drop(on_drop);
+        }
+
+        let sr = sr(r).read();
+        if sr.rxne() {
+            unsafe { rdr(r).read_volatile() };
+        }
+        if sr.abre() || !sr.abrf() {
+            return Err(Error::BaudrateDetection);
+        }
+
+        Ok(baudrate_from_brr(
+            self.kernel_clock,
+            r.brr().read().0,
+            r.cr3().read().over8().to_bits() == 1,
+        ))
+    }
+
+    /// Initiate an asynchronous read with idle line detection enabled
+    pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        self.inner_read(buffer, true).await
+    }
+
+    /// Initiate an asynchronous read of 9-bit words, see [`DataBits::DataBits9`]
+    pub async fn read_u16(&mut self, buffer: &mut [u16]) -> Result<(), Error> {
+        self.inner_read_u16(buffer, false).await?;
+
+        Ok(())
+    }
+
+    /// Initiate an asynchronous read of 9-bit words with idle line detection enabled
+    pub async fn read_until_idle_u16(&mut self, buffer: &mut [u16]) -> Result<usize, Error> {
+        self.inner_read_u16(buffer, true).await
     }
 }
 
@@ -971,6 +1617,19 @@ impl<'d, M: Mode> UartRx<'d, M> {
 
     /// Perform a blocking read into `buffer`
     pub fn blocking_read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.blocking_read_inner(buffer, |r| unsafe { rdr(r).read_volatile() })
+    }
+
+    /// Perform a blocking read of 9-bit words into `buffer`, see [`DataBits::DataBits9`]
+    pub fn blocking_read_u16(&mut self, buffer: &mut [u16]) -> Result<(), Error> {
+        self.blocking_read_inner(buffer, |r| unsafe { rdr_u16(r).read_volatile() })
+    }
+
+    fn blocking_read_inner<W>(
+        &mut self,
+        buffer: &mut [W],
+        read: impl Fn(Regs) -> W,
+    ) -> Result<(), Error> {
         let r = self.info.regs;
 
         // Call flush for Half-Duplex mode if some bytes were written and flush was not called.
@@ -987,17 +1646,59 @@ impl<'d, M: Mode> UartRx<'d, M> {
 
         for b in buffer {
             while !self.check_rx_flags()? {}
-            unsafe { *b = rdr(r).read_volatile() }
+            *b = read(r);
         }
         Ok(())
     }
+
+    /// Put the receiver in mute mode.
+    ///
+    /// Received characters are ignored until the receiver is woken up by the configured
+    /// [`Config::wakeup_method`]: an idle line, or an address character carrying the own
+    /// [`Config::address`] in its low 4 bits. With address-mark wakeup, the matching address
+    /// character is the first one received.
+    pub fn enter_mute_mode(&mut self) {
+        critical_section::with(|_| self.info.regs.cr1().modify(|w| w.set_rwu(true)));
+    }
+
+    /// Leave mute mode without waiting for the wakeup condition.
+    pub fn exit_mute_mode(&mut self) {
+        critical_section::with(|_| self.info.regs.cr1().modify(|w| w.set_rwu(false)));
+    }
+
+    /// Returns true while the receiver is in mute mode.
+    pub fn is_muted(&self) -> bool {
+        self.info.regs.cr1().read().rwu()
+    }
+
+    /// Enter Stop mode until the start bit of the next character arrives on RX.
+    ///
+    /// The USART cannot wake the chip from Stop mode by itself, so the start bit is caught on
+    /// `exti`, the EXTI channel of the RX pin. The system clock is restored before this
+    /// returns, and reception continues with the next character.
+    ///
+    /// The character that woke the chip is lost or received with an error. Its start bit
+    /// arrives while the USART is stopped, and the rest of it while the chip runs from HSI,
+    /// before [`rcc`](crate::rcc) has restored the PLL or HSE, so the USART also samples it at
+    /// the wrong baud rate unless the system clock is HSI. Have the sender precede its message
+    /// with a preamble byte, e.g. `0xFF`, and discard what the first read returns.
+    ///
+    /// Other interrupts also wake the chip; they are serviced and the chip is stopped again.
+    /// Must not be called with interrupts disabled.
+    #[cfg(feature = "exti")]
+    pub fn enter_stop_until_rx(&mut self, exti: Peri<'_, impl exti::Channel>) {
+        low_power::stop_until_rx(self.rx.as_ref(), exti)
+    }
 }
 
 impl<'d, M: Mode> Drop for UartTx<'d, M> {
     fn drop(&mut self) {
         self.tx.as_ref().map(|x| x.set_as_disconnected());
         self.cts.as_ref().map(|x| x.set_as_disconnected());
-        self.de.as_ref().map(|x| x.set_as_disconnected());
+        // The DE pin itself is disconnected when the `Flex` is dropped.
+        if self.de.is_some() {
+            self.state.de.clear();
+        }
         drop_tx_rx(self.info, self.state);
     }
 }
@@ -1073,6 +1774,37 @@ impl<'d> Uart<'d, Async> {
         )
     }
 
+    /// Create a new bidirectional UART driving an RS-485 transceiver's driver enable (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    ///
+    /// This is also how to get a [`RingBufferedUartRx`] on an RS-485 bus: DE is only driven
+    /// while transmitting, so it stays with the transmit half returned by [`split`][Self::split],
+    /// and the receive half can be turned into a ring-buffered receiver as usual.
+    pub fn new_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        de: Peri<'d, impl DePin<T>>,
+        tx_dma: Peri<'d, impl TxDma<T>>,
+        rx_dma: Peri<'d, impl RxDma<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, config.rx_af()),
+            new_pin!(tx, config.tx_af()),
+            None,
+            None,
+            config.de_pin(de),
+            new_dma!(tx_dma),
+            new_dma!(rx_dma),
+            config,
+        )
+    }
+
     /// Create a single-wire half-duplex Uart transceiver on a single Tx pin.
     ///
     /// See [`new_half_duplex_on_rx`][`Self::new_half_duplex_on_rx`] if you would prefer to use an Rx pin
@@ -1108,7 +1840,104 @@ impl<'d> Uart<'d, Async> {
             config,
         )
     }
+}
+
+#[cfg(not(dma))]
+impl<'d> Uart<'d, Async> {
+    /// Create a new bidirectional UART
+    ///
+    /// Bytes are moved by the USART interrupt, for chips without DMA.
+    pub fn new<T: Instance>(
+        peri: Peri<'d, T>,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, config.rx_af()),
+            new_pin!(tx, config.tx_af()),
+            None,
+            None,
+            None,
+            config,
+        )
+    }
+
+    /// Create a new bidirectional UART with request-to-send and clear-to-send pins
+    pub fn new_with_rtscts<T: Instance>(
+        peri: Peri<'d, T>,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        rts: Peri<'d, impl RtsPin<T>>,
+        cts: Peri<'d, impl CtsPin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, config.rx_af()),
+            new_pin!(tx, config.tx_af()),
+            new_pin!(rts, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(cts, AfType::input(Pull::None)),
+            None,
+            config,
+        )
+    }
+
+    /// Create a new bidirectional UART driving an RS-485 transceiver's driver enable (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    pub fn new_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        de: Peri<'d, impl DePin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, config.rx_af()),
+            new_pin!(tx, config.tx_af()),
+            None,
+            None,
+            config.de_pin(de),
+            config,
+        )
+    }
+
+    /// Create a single-wire half-duplex Uart transceiver on a single Tx pin.
+    ///
+    /// The TX pin is always released when no data is transmitted. Thus, it acts as a standard
+    /// I/O in idle or in reception.
+    /// Apart from this, the communication protocol is similar to normal USART mode. Any conflict
+    /// on the line must be managed by software (for instance by using a centralized arbiter).
+    #[doc(alias("HDSEL"))]
+    pub fn new_half_duplex<T: Instance>(
+        peri: Peri<'d, T>,
+        tx: Peri<'d, impl TxPin<T>>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        mut config: Config,
+        half_duplex: HalfDuplexConfig,
+    ) -> Result<Self, ConfigError> {
+        config.half_duplex = true;
+
+        Self::new_inner(
+            peri,
+            None,
+            new_pin!(tx, half_duplex.af_type()),
+            None,
+            None,
+            None,
+            config,
+        )
+    }
+}
 
+impl<'d> Uart<'d, Async> {
     /// Perform an asynchronous write
     pub async fn write(&mut self, buffer: &[u8]) -> Result<(), Error> {
         self.tx.write(buffer).await
@@ -1128,6 +1957,28 @@ impl<'d> Uart<'d, Async> {
     pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
         self.rx.read_until_idle(buffer).await
     }
+
+    /// Perform an asynchronous write of 9-bit words
+    pub async fn write_u16(&mut self, buffer: &[u16]) -> Result<(), Error> {
+        self.tx.write_u16(buffer).await
+    }
+
+    /// Perform an asynchronous read of 9-bit words into `buffer`
+    pub async fn read_u16(&mut self, buffer: &mut [u16]) -> Result<(), Error> {
+        self.rx.read_u16(buffer).await
+    }
+
+    /// Perform an asynchronous read of 9-bit words with idle line detection enabled
+    pub async fn read_until_idle_u16(&mut self, buffer: &mut [u16]) -> Result<usize, Error> {
+        self.rx.read_until_idle_u16(buffer).await
+    }
+
+    /// Wait for automatic baud rate detection and return the measured baud rate.
+    ///
+    /// See [`UartRx::detect_baudrate`].
+    pub async fn detect_baudrate(&mut self) -> Result<u32, Error> {
+        self.rx.detect_baudrate().await
+    }
 }
 
 impl<'d> Uart<'d, Blocking> {
@@ -1177,6 +2028,33 @@ impl<'d> Uart<'d, Blocking> {
         )
     }
 
+    /// Create a new blocking bidirectional UART driving an RS-485 transceiver's driver enable
+    /// (DE) pin.
+    ///
+    /// The USART has no driver enable logic, so `de` is driven as a GPIO, see
+    /// [`Config::de_polarity`]. Writes wait for the transmission to complete and then release it.
+    pub fn new_blocking_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        rx: Peri<'d, impl RxPin<T>>,
+        tx: Peri<'d, impl TxPin<T>>,
+        de: Peri<'d, impl DePin<T>>,
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(rx, config.rx_af()),
+            new_pin!(tx, config.tx_af()),
+            None,
+            None,
+            config.de_pin(de),
+            #[cfg(dma)]
+            None,
+            #[cfg(dma)]
+            None,
+            config,
+        )
+    }
+
     /// Create a single-wire half-duplex Uart transceiver on a single Tx pin.
     ///
     /// See [`new_half_duplex_on_rx`][`Self::new_half_duplex_on_rx`] if you would prefer to use an Rx pin
@@ -1219,7 +2097,7 @@ impl<'d, M: Mode> Uart<'d, M> {
         tx: Option<Peri<'d, AnyPin>>,
         rts: Option<Peri<'d, AnyPin>>,
         cts: Option<Peri<'d, AnyPin>>,
-        de: Option<Peri<'d, AnyPin>>,
+        de: Option<Flex<'d>>,
         #[cfg(dma)] tx_dma: Option<ChannelAndRequest<'d>>,
         #[cfg(dma)] rx_dma: Option<ChannelAndRequest<'d>>,
         config: Config,
@@ -1269,6 +2147,9 @@ impl<'d, M: Mode> Uart<'d, M> {
             w.set_ctse(self.tx.cts.is_some());
         });
         configure(info, self.rx.kernel_clock, config, true, true)?;
+        if let Some(de) = &self.tx.de {
+            state.de.set(de, config);
+        }
 
         info.interrupt.unpend();
         unsafe { info.interrupt.enable() };
@@ -1281,6 +2162,11 @@ impl<'d, M: Mode> Uart<'d, M> {
         self.tx.blocking_write(buffer)
     }
 
+    /// Perform a blocking write of 9-bit words
+    pub fn blocking_write_u16(&mut self, buffer: &[u16]) -> Result<(), Error> {
+        self.tx.blocking_write_u16(buffer)
+    }
+
     /// Block until transmission complete
     pub fn blocking_flush(&mut self) -> Result<(), Error> {
         self.tx.blocking_flush()
@@ -1296,6 +2182,32 @@ impl<'d, M: Mode> Uart<'d, M> {
         self.rx.blocking_read(buffer)
     }
 
+    /// Perform a blocking read of 9-bit words into `buffer`
+    pub fn blocking_read_u16(&mut self, buffer: &mut [u16]) -> Result<(), Error> {
+        self.rx.blocking_read_u16(buffer)
+    }
+
+    /// Put the receiver in mute mode, see [`UartRx::enter_mute_mode`].
+    pub fn enter_mute_mode(&mut self) {
+        self.rx.enter_mute_mode()
+    }
+
+    /// Leave mute mode without waiting for the wakeup condition.
+    pub fn exit_mute_mode(&mut self) {
+        self.rx.exit_mute_mode()
+    }
+
+    /// Returns true while the receiver is in mute mode.
+    pub fn is_muted(&self) -> bool {
+        self.rx.is_muted()
+    }
+
+    /// Enter Stop mode until a character arrives, see [`UartRx::enter_stop_until_rx`].
+    #[cfg(feature = "exti")]
+    pub fn enter_stop_until_rx(&mut self, exti: Peri<'_, impl exti::Channel>) {
+        self.rx.enter_stop_until_rx(exti)
+    }
+
     /// Split the Uart into a transmitter and receiver, which is
     /// particularly useful when having two tasks correlating to
     /// transmitting and receiving.
@@ -1322,6 +2234,17 @@ fn reconfigure(info: &Info, kernel_clock: Hertz, config: &Config) -> Result<(),
     Ok(())
 }
 
+/// Baud rate programmed in BRR, either by `configure` or by auto baud rate detection.
+fn baudrate_from_brr(kernel_clock: Hertz, brr: u32, over8: bool) -> u32 {
+    if over8 {
+        // BRR[2:0] holds USARTDIV[3:1]
+        let div = (brr & !0xF) | ((brr & 0x07) << 1);
+        kernel_clock.0 * 2 / div.max(1)
+    } else {
+        kernel_clock.0 / brr.max(1)
+    }
+}
+
 fn configure(
     info: &Info,
     kernel_clock: Hertz,
@@ -1336,6 +2259,13 @@ fn configure(
         return Err(ConfigError::RxOrTxNotEnabled);
     }
 
+    // M selects the frame length, which includes the parity bit.
+    let m0 = match (config.data_bits, config.parity) {
+        (DataBits::DataBits8, Parity::ParityNone) => vals::M0::BIT8,
+        (DataBits::DataBits8, _) | (DataBits::DataBits9, Parity::ParityNone) => vals::M0::BIT9,
+        (DataBits::DataBits9, _) => return Err(ConfigError::DataParityNotSupported),
+    };
+
     static DIVS: [(u16, ()); 1] = [(1, ())];
 
     let (mul, brr_min, brr_max) = match kind {
@@ -1405,6 +2335,7 @@ fn configure(
     );
 
     r.cr2().write(|w| {
+        w.set_add(config.address & 0x0F);
         w.set_stop(match config.stop_bits {
             // StopBits::STOP0P5 => vals::Stop::STOP0P5,
             StopBits::STOP1 => vals::Stop::STOP1,
@@ -1416,6 +2347,14 @@ fn configure(
     r.cr3().modify(|w| {
         w.set_hdsel(config.half_duplex);
         w.set_over8(vals::Over8::from_bits(over8 as _));
+        // Detection is (re)armed by setting ABREN while the USART is disabled.
+        w.set_abren(config.auto_baud.is_some());
+        w.set_abrmode(match config.auto_baud {
+            None | Some(AutoBaudMode::StartBit) => 0b00,
+            Some(AutoBaudMode::FallingEdge) => 0b01,
+            Some(AutoBaudMode::Frame7F) => 0b10,
+            Some(AutoBaudMode::Frame55) => 0b11,
+        });
     });
 
     r.cr1().write(|w| {
@@ -1435,14 +2374,11 @@ fn configure(
         }
 
         // configure word size
-        // if using odd or even parity it must be configured to 9bits
-        w.set_m0(if config.parity != Parity::ParityNone {
-            trace!("USART: m0: vals::M0::BIT9");
-            vals::M0::BIT9
-        } else {
-            trace!("USART: m0: vals::M0::BIT8");
-            vals::M0::BIT8
-        });
+        trace!("USART: m0: {}", m0.to_bits());
+        w.set_m0(m0);
+        w.set_wake(vals::Wake::from_bits(
+            (config.wakeup_method == WakeupMethod::AddressMark) as u8,
+        ));
         // configure parity
         w.set_pce(config.parity != Parity::ParityNone);
         w.set_ps(match config.parity {
@@ -1506,6 +2442,7 @@ impl embedded_hal_nb::serial::Error for Error {
             Self::Overrun => embedded_hal_nb::serial::ErrorKind::Overrun,
             Self::Parity => embedded_hal_nb::serial::ErrorKind::Parity,
             Self::BufferTooLong => embedded_hal_nb::serial::ErrorKind::Other,
+            Self::BaudrateDetection => embedded_hal_nb::serial::ErrorKind::Other,
         }
     }
 }
@@ -1590,7 +2527,6 @@ impl<M: Mode> embedded_io::Write for UartTx<'_, M> {
     }
 }
 
-#[cfg(dma)]
 impl embedded_io_async::Write for Uart<'_, Async> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         self.write(buf).await?;
@@ -1602,7 +2538,6 @@ impl embedded_io_async::Write for Uart<'_, Async> {
     }
 }
 
-#[cfg(dma)]
 impl embedded_io_async::Write for UartTx<'_, Async> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         self.write(buf).await?;
@@ -1619,10 +2554,24 @@ pub use buffered::*;
 pub use crate::usart::buffered::InterruptHandler as BufferedInterruptHandler;
 mod buffered;
 
+mod spi;
+pub use spi::{SpiConfig, UsartSpi};
+
+#[cfg(feature = "time")]
+mod lin;
+#[cfg(feature = "time")]
+pub use lin::{ChecksumModel, Lin, LinError};
+
+#[cfg(feature = "exti")]
+mod low_power;
+
 #[cfg(dma)]
 mod ringbuffered;
 #[cfg(dma)]
-pub use ringbuffered::RingBufferedUartRx;
+pub use ringbuffered::{RingBufferedUartRx, RingBufferedUartTx};
+
+#[cfg(all(feature = "modbus", dma))]
+pub mod modbus;
 
 fn tdr(r: crate::pac::usart::Usart) -> *mut u8 {
     r.dr().as_ptr() as _
@@ -1632,6 +2581,14 @@ fn rdr(r: crate::pac::usart::Usart) -> *mut u8 {
     r.dr().as_ptr() as _
 }
 
+fn tdr_u16(r: crate::pac::usart::Usart) -> *mut u16 {
+    r.dr().as_ptr() as _
+}
+
+fn rdr_u16(r: crate::pac::usart::Usart) -> *mut u16 {
+    r.dr().as_ptr() as _
+}
+
 fn sr(r: crate::pac::usart::Usart) -> crate::pac::common::Reg<regs::Sr, crate::pac::common::RW> {
     r.sr()
 }
@@ -1648,6 +2605,18 @@ enum Kind {
 
 struct State {
     rx_waker: AtomicWaker,
+    de: DriverEnable,
+    #[cfg(not(dma))]
+    tx_waker: AtomicWaker,
+    #[cfg(not(dma))]
+    tx: IrqTransfer,
+    #[cfg(not(dma))]
+    rx: IrqTransfer,
+    /// SR value that ended the last interrupt-driven read.
+    #[cfg(not(dma))]
+    rx_sr: AtomicU32,
+    #[cfg(dma)]
+    tx_ring: ringbuffered::TxRing,
     tx_rx_refcount: AtomicU8,
 }
 
@@ -1655,11 +2624,177 @@ impl State {
     const fn new() -> Self {
         Self {
             rx_waker: AtomicWaker::new(),
+            de: DriverEnable::new(),
+            #[cfg(not(dma))]
+            tx_waker: AtomicWaker::new(),
+            #[cfg(not(dma))]
+            tx: IrqTransfer::new(),
+            #[cfg(not(dma))]
+            rx: IrqTransfer::new(),
+            #[cfg(not(dma))]
+            rx_sr: AtomicU32::new(0),
+            #[cfg(dma)]
+            tx_ring: ringbuffered::TxRing::new(),
             tx_rx_refcount: AtomicU8::new(0),
         }
     }
 }
 
+/// RS-485 driver enable, driven as a GPIO.
+///
+/// The USART has no hardware DE output, so the driver asserts the pin before transmitting and
+/// releases it once it has seen transmission complete. Both wait for the configured times in
+/// a busy loop, so neither is called from the interrupt handler, which uses
+/// [`release_now`](Self::release_now) when there is no deassertion time.
+struct DriverEnable {
+    /// `pin_port` of the DE pin, `NO_PIN` if there is none
+    pin: AtomicU8,
+    active_high: AtomicBool,
+    /// Assertion and deassertion times, in CPU cycles
+    assert_cycles: AtomicU32,
+    deassert_cycles: AtomicU32,
+}
+
+impl DriverEnable {
+    const NO_PIN: u8 = u8::MAX;
+
+    const fn new() -> Self {
+        Self {
+            pin: AtomicU8::new(Self::NO_PIN),
+            active_high: AtomicBool::new(true),
+            assert_cycles: AtomicU32::new(0),
+            deassert_cycles: AtomicU32::new(0),
+        }
+    }
+
+    fn set(&self, pin: &Flex<'_>, config: &Config) {
+        let hclk = unsafe { crate::rcc::get_freqs() }
+            .hclk1
+            .to_hertz()
+            .map_or(0, |f| f.0);
+        let cycles = |sixteenths: u8| {
+            (hclk as u64 * sixteenths as u64 / (16 * config.baudrate.max(1) as u64)) as u32
+        };
+
+        self.active_high
+            .store(config.de_polarity == DePolarity::ActiveHigh, Ordering::Relaxed);
+        self.assert_cycles
+            .store(cycles(config.de_assertion_time), Ordering::Relaxed);
+        self.deassert_cycles
+            .store(cycles(config.de_deassertion_time), Ordering::Relaxed);
+        self.pin.store(pin.pin.pin_port(), Ordering::Release);
+    }
+
+    fn clear(&self) {
+        self.pin.store(Self::NO_PIN, Ordering::Release);
+    }
+
+    fn pin(&self) -> Option<AnyPin> {
+        let pin_port = self.pin.load(Ordering::Acquire);
+        (pin_port != Self::NO_PIN).then(|| unsafe { AnyPin::steal(pin_port) })
+    }
+
+    /// Drive DE and wait for the assertion time. Does nothing without a DE pin.
+    fn assert(&self) {
+        if let Some(pin) = self.pin() {
+            if self.active_high.load(Ordering::Relaxed) {
+                pin.set_high();
+            } else {
+                pin.set_low();
+            }
+            cortex_m::asm::delay(self.assert_cycles.load(Ordering::Relaxed));
+        }
+    }
+
+    /// Wait for the deassertion time and release DE. Does nothing without a DE pin.
+    fn release(&self) {
+        if self.pin().is_some() {
+            cortex_m::asm::delay(self.deassert_cycles.load(Ordering::Relaxed));
+        }
+        self.release_now();
+    }
+
+    /// Release DE without waiting for the deassertion time. Does nothing without a DE pin.
+    fn release_now(&self) {
+        if let Some(pin) = self.pin() {
+            if self.active_high.load(Ordering::Relaxed) {
+                pin.set_low();
+            } else {
+                pin.set_high();
+            }
+        }
+    }
+
+    /// Whether [`release`](Self::release) has a deassertion time to wait for.
+    fn has_deassertion_time(&self) -> bool {
+        self.pin().is_some() && self.deassert_cycles.load(Ordering::Relaxed) != 0
+    }
+}
+
+/// Buffer handed to the interrupt handler by the async driver on chips without DMA.
+///
+/// Only load/store atomics are used, the thread side sets the buffer up before enabling the
+/// interrupt and tears it down after disabling it.
+#[cfg(not(dma))]
+struct IrqTransfer {
+    ptr: AtomicPtr<u8>,
+    len: AtomicUsize,
+    pos: AtomicUsize,
+    /// The buffer holds `u16` words, for 9 data bits
+    wide: AtomicBool,
+}
+
+#[cfg(not(dma))]
+impl IrqTransfer {
+    const fn new() -> Self {
+        Self {
+            ptr: AtomicPtr::new(core::ptr::null_mut()),
+            len: AtomicUsize::new(0),
+            pos: AtomicUsize::new(0),
+            wide: AtomicBool::new(false),
+        }
+    }
+
+    /// Hand `len` words at `ptr` to the interrupt handler.
+    fn start(&self, ptr: *mut u8, len: usize, wide: bool) {
+        self.ptr.store(ptr, Ordering::Relaxed);
+        self.wide.store(wide, Ordering::Relaxed);
+        self.pos.store(0, Ordering::Relaxed);
+        self.len.store(len, Ordering::Relaxed);
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn stop(&self) {
+        self.len.store(0, Ordering::Relaxed);
+        self.pos.store(0, Ordering::Relaxed);
+        self.ptr.store(core::ptr::null_mut(), Ordering::Relaxed);
+    }
+
+    fn transferred(&self) -> usize {
+        self.pos.load(Ordering::Relaxed)
+    }
+
+    fn remaining(&self) -> usize {
+        self.len.load(Ordering::Relaxed) - self.pos.load(Ordering::Relaxed)
+    }
+
+    fn wide(&self) -> bool {
+        self.wide.load(Ordering::Relaxed)
+    }
+
+    /// Claim the next word of the buffer. Only called from the interrupt handler.
+    fn next(&self) -> Option<*mut u8> {
+        let pos = self.pos.load(Ordering::Relaxed);
+        if pos >= self.len.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.pos.store(pos + 1, Ordering::Relaxed);
+        let offset = if self.wide() { pos * 2 } else { pos };
+        // The buffer outlives the transfer, see `start` and `stop`.
+        Some(unsafe { self.ptr.load(Ordering::Relaxed).add(offset) })
+    }
+}
+
 struct Info {
     regs: Regs,
     rcc: RccInfo,
@@ -1732,3 +2867,19 @@ foreach_interrupt!(
         impl_usart!($inst, $irq, Kind::Uart);
     };
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baudrate_from_brr() {
+        // 8 MHz / 9600 baud, oversampling by 16: USARTDIV = 833.33
+        assert_eq!(baudrate_from_brr(Hertz::mhz(8), 833, false), 9603);
+        // Oversampling by 8: USARTDIV = 0x2A is programmed as 0x25, BRR[2:0] = USARTDIV[3:1]
+        assert_eq!(baudrate_from_brr(Hertz::mhz(8), 0x25, true), 380952);
+        assert_eq!(baudrate_from_brr(Hertz::mhz(8), 0x10, true), 1_000_000);
+        // BRR is never 0 once configured, but must not divide by zero
+        assert_eq!(baudrate_from_brr(Hertz::mhz(8), 0, false), 8_000_000);
+    }
+}
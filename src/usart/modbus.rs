@@ -0,0 +1,494 @@
+//! Modbus RTU framing and slave
+//!
+//! Frames are received with a [`RingBufferedUartRx`], so no character is lost between reads,
+//! and sent with a [`UartTx`], which should drive the DE pin of the RS-485 transceiver (see
+//! [`UartTx::new_with_de`]).
+//!
+//! The end of a frame (t3.5) is detected by the USART idle line interrupt, which fires after one
+//! idle character time, followed by a timer for the rest of the gap. A gap longer than t1.5
+//! inside a frame makes the frame invalid, as required by the Modbus serial line specification.
+//!
+//! The UART should be configured for 8 data bits with even parity (the Modbus default), or
+//! with no parity and 2 stop bits.
+
+use embassy_time::{with_timeout, Duration, Instant};
+
+use super::{ConfigError, RingBufferedUartRx, UartTx};
+use crate::mode::Async;
+
+/// Broadcast slave address. Requests to it are processed but never answered.
+pub const BROADCAST_ADDRESS: u8 = 0;
+
+/// Maximum size of an RTU frame: address, 253 bytes of PDU and CRC.
+pub const MAX_FRAME_LEN: usize = 256;
+
+/// Modbus RTU error
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Error reported by the underlying UART
+    Uart(super::Error),
+    /// The frame is shorter than address, function code and CRC
+    FrameTooShort,
+    /// The frame received, or the one a response PDU would make, is longer than
+    /// [`MAX_FRAME_LEN`]
+    FrameTooLong,
+    /// A gap longer than t1.5 was detected inside the frame
+    InterCharacterGap,
+    /// The frame CRC does not match
+    Crc,
+}
+
+impl From<super::Error> for Error {
+    fn from(e: super::Error) -> Self {
+        Self::Uart(e)
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Uart(e) => write!(f, "{}", e),
+            Self::FrameTooShort => write!(f, "Frame too short"),
+            Self::FrameTooLong => write!(f, "Frame too long"),
+            Self::InterCharacterGap => write!(f, "Inter-character gap inside frame"),
+            Self::Crc => write!(f, "CRC error"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Modbus exception code, returned to the master in an exception response
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Exception {
+    /// The function code is not supported
+    IllegalFunction = 0x01,
+    /// The data address is not valid for this device
+    IllegalDataAddress = 0x02,
+    /// A value in the request is not valid
+    IllegalDataValue = 0x03,
+    /// The device failed to perform the action
+    ServerDeviceFailure = 0x04,
+}
+
+/// Data model of a Modbus slave.
+///
+/// Every method defaults to [`Exception::IllegalDataAddress`], so an implementation only
+/// provides the tables it has. Multi-item requests call the method once per item.
+pub trait Handler {
+    /// Read coil `address` (function codes 0x01)
+    fn read_coil(&mut self, address: u16) -> Result<bool, Exception> {
+        let _ = address;
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Write coil `address` (function codes 0x05 and 0x0F)
+    fn write_coil(&mut self, address: u16, value: bool) -> Result<(), Exception> {
+        let _ = (address, value);
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read discrete input `address` (function code 0x02)
+    fn read_discrete_input(&mut self, address: u16) -> Result<bool, Exception> {
+        let _ = address;
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read holding register `address` (function code 0x03)
+    fn read_holding_register(&mut self, address: u16) -> Result<u16, Exception> {
+        let _ = address;
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Write holding register `address` (function codes 0x06 and 0x10)
+    fn write_holding_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+        let _ = (address, value);
+        Err(Exception::IllegalDataAddress)
+    }
+
+    /// Read input register `address` (function code 0x04)
+    fn read_input_register(&mut self, address: u16) -> Result<u16, Exception> {
+        let _ = address;
+        Err(Exception::IllegalDataAddress)
+    }
+}
+
+/// Compute the Modbus CRC-16 of `data`.
+///
+/// The CRC is sent least significant byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &b| {
+        (0..8).fold(crc ^ b as u16, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Character and frame gap times for a baud rate.
+#[derive(Copy, Clone)]
+struct Timing {
+    /// Time of one 11-bit character
+    char: Duration,
+    t1_5: Duration,
+    t3_5: Duration,
+}
+
+impl Timing {
+    fn new(baudrate: u32) -> Self {
+        let char = Duration::from_micros(11 * 1_000_000 / baudrate as u64);
+        if baudrate > 19200 {
+            // Fixed values recommended by the specification for high baud rates.
+            Self {
+                char,
+                t1_5: Duration::from_micros(750),
+                t3_5: Duration::from_micros(1750),
+            }
+        } else {
+            Self {
+                char,
+                t1_5: char * 3 / 2,
+                t3_5: char * 7 / 2,
+            }
+        }
+    }
+}
+
+/// Modbus RTU slave
+pub struct Slave<'d> {
+    rx: RingBufferedUartRx<'d>,
+    tx: UartTx<'d, Async>,
+    address: u8,
+    frame: [u8; MAX_FRAME_LEN],
+}
+
+impl<'d> Slave<'d> {
+    /// Create a slave with `address` (1..=247), or fail with [`ConfigError::ModbusAddress`].
+    ///
+    /// The frame timing follows the baud rate the UART is configured for.
+    pub fn new(
+        rx: RingBufferedUartRx<'d>,
+        tx: UartTx<'d, Async>,
+        address: u8,
+    ) -> Result<Self, ConfigError> {
+        if !(1..=247).contains(&address) {
+            return Err(ConfigError::ModbusAddress);
+        }
+        Ok(Self {
+            rx,
+            tx,
+            address,
+            frame: [0; MAX_FRAME_LEN],
+        })
+    }
+
+    /// Serve requests forever. Invalid frames are dropped, as the specification requires.
+    pub async fn run(&mut self, handler: &mut impl Handler) -> ! {
+        loop {
+            if let Err(e) = self.process(handler).await {
+                warn!("modbus: {}", e);
+            }
+        }
+    }
+
+    /// Receive one request, pass it to `handler` if it is addressed to this slave and send the
+    /// response.
+    pub async fn process(&mut self, handler: &mut impl Handler) -> Result<(), Error> {
+        let len = self.receive().await?;
+
+        let address = self.frame[0];
+        if address != self.address && address != BROADCAST_ADDRESS {
+            return Ok(());
+        }
+
+        let mut response = [0; MAX_FRAME_LEN];
+        let n = handle_request(
+            &self.frame[1..len - 2],
+            &mut response[1..MAX_FRAME_LEN - 2],
+            handler,
+        );
+        if address == BROADCAST_ADDRESS {
+            return Ok(());
+        }
+
+        self.send(&mut response, n).await
+    }
+
+    /// Receive a frame and verify its CRC, for handling requests outside of [`Handler`].
+    ///
+    /// The returned frame includes the slave address and the CRC, and may be addressed to
+    /// another slave.
+    pub async fn receive_frame(&mut self) -> Result<&[u8], Error> {
+        let len = self.receive().await?;
+        Ok(&self.frame[..len])
+    }
+
+    /// Send a response `pdu` (function code and data), adding the slave address and CRC.
+    ///
+    /// Fails with [`Error::FrameTooLong`] if `pdu` is longer than 253 bytes.
+    pub async fn send_response(&mut self, pdu: &[u8]) -> Result<(), Error> {
+        let n = pdu.len();
+        if n > MAX_FRAME_LEN - 3 {
+            return Err(Error::FrameTooLong);
+        }
+
+        let mut response = [0; MAX_FRAME_LEN];
+        response[1..n + 1].copy_from_slice(pdu);
+        self.send(&mut response, n).await
+    }
+
+    /// Add address and CRC around the `n` byte PDU at `frame[1..]` and send it.
+    async fn send(&mut self, frame: &mut [u8; MAX_FRAME_LEN], n: usize) -> Result<(), Error> {
+        frame[0] = self.address;
+        let crc = crc16(&frame[..n + 1]);
+        frame[n + 1..n + 3].copy_from_slice(&crc.to_le_bytes());
+
+        // The request was followed by t3.5 of silence already, so the response can start now.
+        self.tx.write(&frame[..n + 3]).await?;
+        self.tx.flush().await?;
+        Ok(())
+    }
+
+    /// Receive a frame into `self.frame`, verify its CRC and return its length.
+    async fn receive(&mut self) -> Result<usize, Error> {
+        let timing = Timing::new(self.rx.baudrate());
+
+        let mut len = self.rx.read(&mut self.frame).await?;
+        let mut last = Instant::now();
+        let mut overflow = false;
+        let mut gap = false;
+
+        loop {
+            let mut discard = [0; 16];
+            let buf = match self.frame.get_mut(len..) {
+                Some(buf) if !buf.is_empty() => buf,
+                _ => &mut discard[..],
+            };
+
+            // `read` returns when the line has been idle for one character, so waiting the
+            // rest of t3.5 without new data marks the end of the frame.
+            let n = match with_timeout(timing.t3_5 - timing.char, self.rx.read(buf)).await {
+                Ok(n) => n?,
+                Err(_) => break,
+            };
+
+            // The new characters took `n` character times to arrive, anything more was silence.
+            let now = Instant::now();
+            let silence = (now - last).checked_sub(timing.char * n as u32);
+            if silence.is_some_and(|s| s > timing.t1_5) {
+                gap = true;
+            }
+            last = now;
+
+            if len + n > MAX_FRAME_LEN {
+                overflow = true;
+            }
+            len = (len + n).min(MAX_FRAME_LEN);
+        }
+
+        if overflow {
+            return Err(Error::FrameTooLong);
+        }
+        if gap {
+            return Err(Error::InterCharacterGap);
+        }
+        if len < 4 {
+            return Err(Error::FrameTooShort);
+        }
+        let crc = u16::from_le_bytes([self.frame[len - 2], self.frame[len - 1]]);
+        if crc16(&self.frame[..len - 2]) != crc {
+            return Err(Error::Crc);
+        }
+        Ok(len)
+    }
+}
+
+/// Process the request PDU `req` and write the response PDU to `resp`, returning its length.
+fn handle_request(req: &[u8], resp: &mut [u8], handler: &mut impl Handler) -> usize {
+    let function = req[0];
+    match handle_function(req, resp, handler) {
+        Ok(n) => n,
+        Err(e) => {
+            resp[0] = function | 0x80;
+            resp[1] = e as u8;
+            2
+        }
+    }
+}
+
+fn handle_function(
+    req: &[u8],
+    resp: &mut [u8],
+    handler: &mut impl Handler,
+) -> Result<usize, Exception> {
+    let function = req[0];
+    let word = |i: usize| {
+        req.get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+    // Check that `count` items starting at `start` are inside the 16-bit address space.
+    let check_range = |start: u16, count: u16, max: u16| {
+        if count == 0 || count > max {
+            Err(Exception::IllegalDataValue)
+        } else if start as u32 + count as u32 > 0x1_0000 {
+            Err(Exception::IllegalDataAddress)
+        } else {
+            Ok(())
+        }
+    };
+
+    resp[0] = function;
+    match function {
+        // Read coils / discrete inputs
+        0x01 | 0x02 => {
+            let (start, count) = (word(1)?, word(3)?);
+            check_range(start, count, 2000)?;
+            let bytes = count.div_ceil(8) as usize;
+            resp[1] = bytes as u8;
+            resp[2..2 + bytes].fill(0);
+            for i in 0..count {
+                let address = start + i;
+                let bit = match function {
+                    0x01 => handler.read_coil(address)?,
+                    _ => handler.read_discrete_input(address)?,
+                };
+                resp[2 + i as usize / 8] |= (bit as u8) << (i % 8);
+            }
+            Ok(2 + bytes)
+        }
+        // Read holding / input registers
+        0x03 | 0x04 => {
+            let (start, count) = (word(1)?, word(3)?);
+            check_range(start, count, 125)?;
+            resp[1] = (count * 2) as u8;
+            for i in 0..count {
+                let address = start + i;
+                let value = match function {
+                    0x03 => handler.read_holding_register(address)?,
+                    _ => handler.read_input_register(address)?,
+                };
+                let at = 2 + i as usize * 2;
+                resp[at..at + 2].copy_from_slice(&value.to_be_bytes());
+            }
+            Ok(2 + count as usize * 2)
+        }
+        // Write single coil
+        0x05 => {
+            let (address, value) = (word(1)?, word(3)?);
+            let value = match value {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue),
+            };
+            handler.write_coil(address, value)?;
+            resp[1..5].copy_from_slice(&req[1..5]);
+            Ok(5)
+        }
+        // Write single register
+        0x06 => {
+            let (address, value) = (word(1)?, word(3)?);
+            handler.write_holding_register(address, value)?;
+            resp[1..5].copy_from_slice(&req[1..5]);
+            Ok(5)
+        }
+        // Write multiple coils
+        0x0F => {
+            let (start, count) = (word(1)?, word(3)?);
+            check_range(start, count, 0x7B0)?;
+            let bytes = count.div_ceil(8) as usize;
+            let data = req
+                .get(6..6 + bytes)
+                .filter(|_| req[5] as usize == bytes)
+                .ok_or(Exception::IllegalDataValue)?;
+            for i in 0..count {
+                let bit = data[i as usize / 8] >> (i % 8) & 1 != 0;
+                handler.write_coil(start + i, bit)?;
+            }
+            resp[1..5].copy_from_slice(&req[1..5]);
+            Ok(5)
+        }
+        // Write multiple registers
+        0x10 => {
+            let (start, count) = (word(1)?, word(3)?);
+            check_range(start, count, 123)?;
+            let bytes = count as usize * 2;
+            let data = req
+                .get(6..6 + bytes)
+                .filter(|_| req[5] as usize == bytes)
+                .ok_or(Exception::IllegalDataValue)?;
+            for (i, value) in data.chunks_exact(2).enumerate() {
+                let value = u16::from_be_bytes([value[0], value[1]]);
+                handler.write_holding_register(start + i as u16, value)?;
+            }
+            resp[1..5].copy_from_slice(&req[1..5]);
+            Ok(5)
+        }
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Registers([u16; 4]);
+
+    impl Handler for Registers {
+        fn read_holding_register(&mut self, address: u16) -> Result<u16, Exception> {
+            self.0
+                .get(address as usize)
+                .copied()
+                .ok_or(Exception::IllegalDataAddress)
+        }
+
+        fn write_holding_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+            let reg = self
+                .0
+                .get_mut(address as usize)
+                .ok_or(Exception::IllegalDataAddress)?;
+            *reg = value;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_crc16() {
+        // Read 10 holding registers from slave 1.
+        assert_eq!(
+            crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]).to_le_bytes(),
+            [0xC5, 0xCD]
+        );
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let mut regs = Registers([0x1234, 0, 0, 0xABCD]);
+        let mut resp = [0; 253];
+
+        let n = handle_request(
+            &[0x10, 0x00, 0x01, 0x00, 0x01, 0x02, 0x55, 0xAA],
+            &mut resp,
+            &mut regs,
+        );
+        assert_eq!(&resp[..n], &[0x10, 0x00, 0x01, 0x00, 0x01]);
+
+        let n = handle_request(&[0x03, 0x00, 0x00, 0x00, 0x02], &mut resp, &mut regs);
+        assert_eq!(&resp[..n], &[0x03, 0x04, 0x12, 0x34, 0x55, 0xAA]);
+
+        let n = handle_request(&[0x03, 0x00, 0x03, 0x00, 0x02], &mut resp, &mut regs);
+        assert_eq!(&resp[..n], &[0x83, 0x02]);
+
+        let n = handle_request(&[0x01, 0x00, 0x00, 0x00, 0x01], &mut resp, &mut regs);
+        assert_eq!(&resp[..n], &[0x81, 0x02]);
+
+        let n = handle_request(&[0x2B], &mut resp, &mut regs);
+        assert_eq!(&resp[..n], &[0xAB, 0x01]);
+    }
+}
@@ -5,34 +5,38 @@
 // Special thanks to the Embassy Project and its contributors for their work!
 
 use core::future::poll_fn;
-use core::mem;
-use core::sync::atomic::{compiler_fence, Ordering};
-use core::task::Poll;
+use core::sync::atomic::{compiler_fence, AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering};
+use core::task::{Poll, RawWaker, RawWakerVTable, Waker};
+use core::{mem, ptr};
 
 use embassy_embedded_hal::SetConfig;
 use embassy_hal_internal::Peri;
-use embedded_io_async::ReadReady;
+use embassy_sync::waitqueue::AtomicWaker;
+use embedded_io_async::{ReadReady, WriteReady};
 use futures_util::future::{select, Either};
 
 use super::{
-    clear_interrupt_flags, rdr, reconfigure, sr, Config, ConfigError, Error, Info, State, UartRx,
+    clear_interrupt_flags, rdr, reconfigure, sr, tdr, Config, ConfigError, DePin, DriverEnable,
+    Error, Info, Instance, InterruptHandler, RxDma, RxPin, State, UartRx, UartTx,
 };
-#[cfg(dma)]
-use crate::dma::ReadableRingBuffer;
-use crate::gpio::{AnyPin, SealedPin as _};
+use crate::dma::{AnyChannel, ReadableRingBuffer, WritableRingBuffer};
+use crate::gpio::{AnyPin, Flex, SealedPin as _};
+use crate::interrupt::{self, InterruptExt};
 use crate::mode::Async;
 use crate::time::Hertz;
 use crate::usart::{Regs, Sr};
 
 /// Rx-only Ring-buffered UART Driver
 ///
-/// Created with [UartRx::into_ring_buffered]
+/// Created with [UartRx::into_ring_buffered] or [RingBufferedUartRx::new_with_de]
 pub struct RingBufferedUartRx<'d> {
     info: &'static Info,
     state: &'static State,
     kernel_clock: Hertz,
     rx: Option<Peri<'d, AnyPin>>,
     rts: Option<Peri<'d, AnyPin>>,
+    // Holds an RS-485 DE pin released for as long as the receiver lives.
+    _de: Option<Flex<'d>>,
     ring_buf: ReadableRingBuffer<'d, u8>,
 }
 
@@ -76,17 +80,48 @@ impl<'d> UartRx<'d, Async> {
             kernel_clock,
             rx,
             rts,
+            _de: None,
             ring_buf,
         }
     }
 }
 
 impl<'d> RingBufferedUartRx<'d> {
+    /// Create a new ring-buffered rx-only UART on an RS-485 transceiver, holding its driver
+    /// enable (DE) pin released so that the transceiver receives.
+    ///
+    /// `de` is driven as a GPIO, see [`Config::de_polarity`]. Otherwise this is
+    /// [`UartRx::new`] followed by [`UartRx::into_ring_buffered`].
+    pub fn new_with_de<T: Instance>(
+        peri: Peri<'d, T>,
+        irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        rx: Peri<'d, impl RxPin<T>>,
+        de: Peri<'d, impl DePin<T>>,
+        rx_dma: Peri<'d, impl RxDma<T>>,
+        dma_buf: &'d mut [u8],
+        config: Config,
+    ) -> Result<Self, ConfigError> {
+        let de = config.de_pin(de);
+        let mut this = UartRx::new(peri, irq, rx, rx_dma, config)?.into_ring_buffered(dma_buf);
+        this._de = de;
+        Ok(this)
+    }
+
     /// Reconfigure the driver
     pub fn set_config(&mut self, config: &Config) -> Result<(), ConfigError> {
         reconfigure(self.info, self.kernel_clock, config)
     }
 
+    /// The baud rate programmed in the UART, as set by the configuration.
+    pub(super) fn baudrate(&self) -> u32 {
+        let r = self.info.regs;
+        super::baudrate_from_brr(
+            self.kernel_clock,
+            r.brr().read().0,
+            r.cr3().read().over8().to_bits() == 1,
+        )
+    }
+
     /// Configure and start the DMA backed UART receiver
     ///
     /// Note: This is also done automatically by [`read()`] if required.
@@ -290,3 +325,388 @@ impl ReadReady for RingBufferedUartRx<'_> {
         Ok(len > 0)
     }
 }
+
+/// Tx-only Ring-buffered UART Driver
+///
+/// Created with [UartTx::into_ring_buffered]. The DMA channel loops over the ring buffer in
+/// circular mode for as long as the driver lives, so [`write`](Self::write) only copies into
+/// the ring. The USART interrupt handler follows the channel and gates its requests once it
+/// has fetched the last queued byte, so the line goes idle instead of repeating stale data,
+/// and the next write lifts the gate again. It runs on the half and full transfer interrupts
+/// of the channel, and on every character once the end of the queued bytes comes before the
+/// next of those; it must not be held off for a character time.
+///
+/// With an RS-485 driver enable pin, DE is asserted when transmission starts, and the interrupt
+/// handler releases it once the last queued byte has left the shift register. With a
+/// [`Config::de_deassertion_time`], which it cannot wait for, DE is released by
+/// [`flush`](Self::flush) instead; call it before expecting other nodes to answer.
+pub struct RingBufferedUartTx<'d> {
+    info: &'static Info,
+    state: &'static State,
+    kernel_clock: Hertz,
+    tx: Option<Peri<'d, AnyPin>>,
+    cts: Option<Peri<'d, AnyPin>>,
+    de: Option<Flex<'d>>,
+    // Owns the running DMA channel, the interrupt handler gates it through `TxRing`.
+    _ring_buf: WritableRingBuffer<'d, u8>,
+}
+
+impl<'d> SetConfig for RingBufferedUartTx<'d> {
+    type Config = Config;
+    type ConfigError = ConfigError;
+
+    fn set_config(&mut self, config: &Self::Config) -> Result<(), Self::ConfigError> {
+        self.set_config(config)
+    }
+}
+
+impl<'d> UartTx<'d, Async> {
+    /// Turn the `UartTx` into a buffered uart which transmits from `buf` in the background.
+    ///
+    /// The USART interrupt gates the DMA requests, so [`InterruptHandler`] must be bound
+    /// with `bind_interrupts!` even if this `UartTx` was created without it, e.g. by taking it
+    /// from [`Uart::split`](super::Uart::split).
+    pub fn into_ring_buffered(mut self, buf: &'d mut [u8]) -> RingBufferedUartTx<'d> {
+        assert!(!buf.is_empty() && buf.len() <= 0xFFFF);
+
+        let r = self.info.regs;
+        let tx_dma = self.tx_dma.take().unwrap();
+        let ring = &self.state.tx_ring;
+        ring.channel.store(tx_dma.channel.id, Ordering::Relaxed);
+        ring.head.store(0, Ordering::Relaxed);
+        ring.tail.store(0, Ordering::Relaxed);
+        ring.active.store(false, Ordering::Relaxed);
+        ring.buf.store(buf.as_mut_ptr(), Ordering::Relaxed);
+        ring.cap.store(buf.len(), Ordering::Relaxed);
+
+        let opts = Default::default();
+        let mut ring_buf =
+            unsafe { WritableRingBuffer::new(tx_dma.channel, tx_dma.request, tdr(r), buf, opts) };
+        ring_buf.set_waker(&interrupt_waker(self.info));
+        // The channel runs from now on, but only fetches while DMAT is set.
+        r.cr3().modify(|w| w.set_dmat(false));
+        ring_buf.start();
+
+        self.info.interrupt.unpend();
+        unsafe { self.info.interrupt.enable() };
+
+        let this = RingBufferedUartTx {
+            info: self.info,
+            state: self.state,
+            kernel_clock: self.kernel_clock,
+            tx: self.tx.take(),
+            cts: self.cts.take(),
+            de: self.de.take(),
+            _ring_buf: ring_buf,
+        };
+
+        // Don't disable the clock
+        mem::forget(self);
+
+        this
+    }
+}
+
+impl<'d> RingBufferedUartTx<'d> {
+    /// Reconfigure the driver
+    pub fn set_config(&mut self, config: &Config) -> Result<(), ConfigError> {
+        reconfigure(self.info, self.kernel_clock, config)?;
+        if let Some(de) = &self.de {
+            self.state.de.set(de, config);
+        }
+        Ok(())
+    }
+
+    /// Copy as many bytes of `buf` as fit into the ring buffer and start transmitting them.
+    ///
+    /// Returns the number of bytes queued, which is 0 if the ring buffer is full.
+    pub fn write(&mut self, buf: &[u8]) -> usize {
+        let r = self.info.regs;
+        let s = self.state;
+        let ring = &s.tx_ring;
+        // Shared with the interrupt handler, which follows the DMA channel on its own.
+        critical_section::with(|_| ring.service(r));
+        let n = ring.push(buf);
+        if n > 0 {
+            // Asserting DE waits for the assertion time, which stays out of the critical
+            // section. `start` refuses to start an idle ring before that.
+            let mut de_asserted = false;
+            while !critical_section::with(|_| ring.start(r, de_asserted)) {
+                s.de.assert();
+                de_asserted = true;
+            }
+        }
+        n
+    }
+
+    /// Queue all of `buf`, waiting for space in the ring buffer as needed.
+    ///
+    /// Returns once the last byte is queued, use [`flush`](Self::flush) to wait for it to be sent.
+    pub async fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            let n = self.write_some(buf).await;
+            buf = &buf[n..];
+        }
+        Ok(())
+    }
+
+    /// Wait until the ring buffer is drained and the last byte has left the shift register,
+    /// then release DE if the interrupt handler left that to us.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        let ring = &self.state.tx_ring;
+        poll_fn(|cx| {
+            ring.waker.register(cx.waker());
+            match ring.active.load(Ordering::Relaxed) && !ring.release_due.load(Ordering::Relaxed) {
+                true => Poll::Pending,
+                false => Poll::Ready(()),
+            }
+        })
+        .await;
+
+        let release = critical_section::with(|_| {
+            let due = ring.release_due.load(Ordering::Relaxed);
+            ring.release_due.store(false, Ordering::Relaxed);
+            due
+        });
+        if release {
+            self.state.de.release();
+            ring.active.store(false, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Queue at least one byte of `buf`, waiting for space if the ring buffer is full.
+    async fn write_some(&mut self, buf: &[u8]) -> usize {
+        if buf.is_empty() {
+            return 0;
+        }
+        poll_fn(|cx| {
+            self.state.tx_ring.waker.register(cx.waker());
+            match self.write(buf) {
+                0 => Poll::Pending,
+                n => Poll::Ready(n),
+            }
+        })
+        .await
+    }
+}
+
+impl Drop for RingBufferedUartTx<'_> {
+    fn drop(&mut self) {
+        let r = self.info.regs;
+        let s = self.state;
+        let ring = &s.tx_ring;
+        // The channel itself is stopped when `_ring_buf` is dropped.
+        critical_section::with(|_| {
+            ring.active.store(false, Ordering::Relaxed);
+            ring.release_due.store(false, Ordering::Relaxed);
+            r.cr1().modify(|w| {
+                w.set_txeie(false);
+                w.set_tcie(false);
+            });
+            r.cr3().modify(|w| w.set_dmat(false));
+        });
+        ring.cap.store(0, Ordering::Relaxed);
+        ring.buf.store(ptr::null_mut(), Ordering::Relaxed);
+
+        self.tx.as_ref().map(|x| x.set_as_disconnected());
+        self.cts.as_ref().map(|x| x.set_as_disconnected());
+        // The DE pin itself is disconnected when the `Flex` is dropped.
+        if self.de.is_some() {
+            s.de.release();
+            s.de.clear();
+        }
+        super::drop_tx_rx(self.info, self.state);
+    }
+}
+
+/// Transmit ring shared between [`RingBufferedUartTx`] and the interrupt handler.
+///
+/// The DMA channel loops over the buffer on its own, this tracks which part of it holds queued
+/// bytes. `head` and `tail` count modulo twice the capacity, so that a full ring can be told
+/// from an empty one. Only load/store atomics are used: the writer alone advances `head`, and
+/// `tail` and the USART registers are only touched by the interrupt handler or in a critical
+/// section.
+pub(super) struct TxRing {
+    buf: AtomicPtr<u8>,
+    cap: AtomicUsize,
+    /// DMA channel id, set by `into_ring_buffered`
+    channel: AtomicU8,
+    /// Where the writer copies the next byte
+    head: AtomicUsize,
+    /// Where the DMA channel was when the ring was last serviced
+    tail: AtomicUsize,
+    /// Set from the start of a transmission until DE is released, or TC has been seen with the
+    /// ring drained without a DE pin
+    active: AtomicBool,
+    /// TC has been seen with the ring drained, DE waits for the deassertion time in `flush`
+    release_due: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl TxRing {
+    pub(super) const fn new() -> Self {
+        Self {
+            buf: AtomicPtr::new(ptr::null_mut()),
+            cap: AtomicUsize::new(0),
+            channel: AtomicU8::new(0),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            active: AtomicBool::new(false),
+            release_due: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    fn channel(&self) -> AnyChannel {
+        AnyChannel {
+            id: self.channel.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of queued bytes the DMA channel had not fetched when the ring was last serviced.
+    fn pending(&self) -> usize {
+        let wrap = 2 * self.cap.load(Ordering::Relaxed);
+        (self.head.load(Ordering::Acquire) + wrap - self.tail.load(Ordering::Relaxed)) % wrap
+    }
+
+    /// Copy as much of `data` as fits into the ring. Only called by the writer.
+    fn push(&self, data: &[u8]) -> usize {
+        let cap = self.cap.load(Ordering::Relaxed);
+        let buf = self.buf.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Relaxed);
+        let n = (cap - self.pending()).min(data.len());
+        for (i, &b) in data[..n].iter().enumerate() {
+            unsafe { buf.add((head + i) % cap).write_volatile(b) };
+        }
+        self.head.store((head + n) % (2 * cap), Ordering::Release);
+        n
+    }
+
+    /// Lift the gate on the DMA requests for newly queued bytes. Runs in a critical section.
+    ///
+    /// Returns `false` without starting if the ring is idle and DE has not been asserted yet.
+    fn start(&self, r: Regs, de_asserted: bool) -> bool {
+        if !self.active.load(Ordering::Relaxed) && !de_asserted {
+            return false;
+        }
+        // A pending release is cancelled, DE stays asserted for the new bytes.
+        self.release_due.store(false, Ordering::Relaxed);
+        self.active.store(true, Ordering::Relaxed);
+        if r.cr3().read().dmat() {
+            // Still sending, the interrupt handler picks the new bytes up.
+            return true;
+        }
+
+        if self.pending() == 1 && sr(r).read().tc() {
+            // On an idle line DR moves to the shift register at once, and the channel would
+            // fetch the stale byte behind this one before the interrupt handler can stop it.
+            // An idle frame holds the byte in DR for a character time instead.
+            r.cr1().modify(|w| w.set_te(false));
+            r.cr1().modify(|w| w.set_te(true));
+        }
+        sr(r).modify(|w| w.set_tc(false));
+        r.cr1().modify(|w| {
+            w.set_tcie(false);
+            w.set_txeie(true);
+        });
+        r.cr3().modify(|w| w.set_dmat(true));
+        true
+    }
+
+    /// Account for the bytes the DMA channel has fetched, and gate its requests as soon as it
+    /// has fetched the last queued one. Runs in the interrupt handler or a critical section.
+    fn service(&self, r: Regs) {
+        if !r.cr3().read().dmat() {
+            return;
+        }
+
+        let cap = self.cap.load(Ordering::Relaxed);
+        let pos = (cap - self.channel().get_remaining_transfers() as usize) % cap;
+        let tail = self.tail.load(Ordering::Relaxed);
+        let fetched = (pos + cap - tail % cap) % cap;
+        self.tail
+            .store((tail + fetched) % (2 * cap), Ordering::Relaxed);
+
+        let pending = self.pending();
+        if pending == 0 {
+            // The last byte is in DR. Stop the channel before the next TXE makes it fetch a
+            // stale one, and wait for the byte to leave the shift register.
+            r.cr3().modify(|w| w.set_dmat(false));
+            sr(r).modify(|w| w.set_tc(false));
+            r.cr1().modify(|w| {
+                w.set_txeie(false);
+                w.set_tcie(true);
+            });
+        } else {
+            // The channel only interrupts at half and full transfer. If the queued bytes end
+            // before the next of those, follow it on every character instead.
+            let next = if pos < cap / 2 { cap - cap / 2 } else { cap };
+            r.cr1().modify(|w| w.set_txeie(pending <= next - pos));
+        }
+
+        if fetched != 0 {
+            self.waker.wake();
+        }
+    }
+
+    /// Called by the interrupt handler, which the DMA channel also triggers through
+    /// [`interrupt_waker`]. Ends the transmission and releases DE on TC once the ring is
+    /// drained, leaving a deassertion time to `flush`.
+    pub(super) fn on_interrupt(&self, r: Regs, de: &DriverEnable) {
+        if !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+
+        self.service(r);
+        if r.cr1().read().tcie() && sr(r).read().tc() && !r.cr3().read().dmat() {
+            r.cr1().modify(|w| w.set_tcie(false));
+            if de.has_deassertion_time() {
+                self.release_due.store(true, Ordering::Relaxed);
+            } else {
+                de.release_now();
+                self.active.store(false, Ordering::Relaxed);
+            }
+            self.waker.wake();
+        }
+    }
+}
+
+/// Waker that pends the USART interrupt. Registered with the DMA channel of a
+/// [`RingBufferedUartTx`], so that its half and full transfer interrupts run
+/// [`TxRing::on_interrupt`].
+fn interrupt_waker(info: &'static Info) -> Waker {
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, pend, pend, noop);
+
+    unsafe fn clone(info: *const ()) -> RawWaker {
+        RawWaker::new(info, &VTABLE)
+    }
+    unsafe fn pend(info: *const ()) {
+        unsafe { &*(info as *const Info) }.interrupt.pend();
+    }
+    unsafe fn noop(_: *const ()) {}
+
+    unsafe { Waker::from_raw(RawWaker::new(info as *const Info as *const (), &VTABLE)) }
+}
+
+impl embedded_io_async::ErrorType for RingBufferedUartTx<'_> {
+    type Error = Error;
+}
+
+impl embedded_io_async::Write for RingBufferedUartTx<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.write_some(buf).await)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.flush().await
+    }
+}
+
+impl WriteReady for RingBufferedUartTx<'_> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        let ring = &self.state.tx_ring;
+        critical_section::with(|_| ring.service(self.info.regs));
+        Ok(ring.pending() < ring.cap.load(Ordering::Relaxed))
+    }
+}
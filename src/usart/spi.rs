@@ -0,0 +1,447 @@
+//! USART synchronous master mode, an SPI-like bus clocked on the CK pin
+
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+
+use embassy_embedded_hal::SetConfig;
+#[cfg(dma)]
+use embassy_futures::join::join;
+use embassy_hal_internal::Peri;
+use embedded_hal_1::spi::{Mode as SpiMode, Phase, Polarity, MODE_0};
+
+use super::{
+    blocking_flush, configure, drop_tx_rx, rdr, sr, tdr, CkPin, Config, ConfigError, Error, Info,
+    Instance, Regs, RxPin, State, TxPin,
+};
+#[cfg(dma)]
+use super::{flush, InterruptHandler, RxDma, TxDma};
+#[cfg(dma)]
+use crate::dma::{ChannelAndRequest, Transfer};
+use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
+#[cfg(dma)]
+use crate::interrupt::{self, InterruptExt};
+#[cfg(dma)]
+use crate::mode::Async;
+use crate::mode::{Blocking, Mode};
+use crate::pac::usart::vals;
+use crate::time::Hertz;
+
+/// Synchronous mode config
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpiConfig {
+    /// Clock frequency on CK
+    pub frequency: Hertz,
+    /// Clock polarity and phase
+    pub mode: SpiMode,
+    /// Output a clock pulse for the last data bit
+    ///
+    /// Most SPI devices need this, otherwise the last bit of every byte is not clocked.
+    pub last_bit_clock: bool,
+    /// Set the pull configuration for the MISO (RX) pin.
+    pub miso_pull: Pull,
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self {
+            frequency: Hertz(1_000_000),
+            mode: MODE_0,
+            last_bit_clock: true,
+            miso_pull: Pull::None,
+        }
+    }
+}
+
+/// SPI-like bus driver on a USART in synchronous master mode.
+///
+/// TX is used as MOSI, RX as MISO and CK as SCK. The USART always shifts data out LSB first,
+/// use [`u8::reverse_bits`] for devices that expect MSB first. There is no chip select,
+/// drive it with a GPIO (e.g. through `embedded_hal_bus`).
+pub struct UsartSpi<'d, M: Mode> {
+    info: &'static Info,
+    state: &'static State,
+    kernel_clock: Hertz,
+    sck: Option<Peri<'d, AnyPin>>,
+    mosi: Option<Peri<'d, AnyPin>>,
+    miso: Option<Peri<'d, AnyPin>>,
+    #[cfg(dma)]
+    tx_dma: Option<ChannelAndRequest<'d>>,
+    #[cfg(dma)]
+    rx_dma: Option<ChannelAndRequest<'d>>,
+    _phantom: PhantomData<M>,
+}
+
+impl<'d, M: Mode> SetConfig for UsartSpi<'d, M> {
+    type Config = SpiConfig;
+    type ConfigError = ConfigError;
+
+    fn set_config(&mut self, config: &Self::Config) -> Result<(), Self::ConfigError> {
+        self.set_config(config)
+    }
+}
+
+impl<'d> UsartSpi<'d, Blocking> {
+    /// Create a new blocking synchronous master driver.
+    pub fn new_blocking<T: Instance>(
+        peri: Peri<'d, T>,
+        sck: Peri<'d, impl CkPin<T>>,
+        mosi: Peri<'d, impl TxPin<T>>,
+        miso: Peri<'d, impl RxPin<T>>,
+        config: SpiConfig,
+    ) -> Result<Self, ConfigError> {
+        Self::new_inner(
+            peri,
+            new_pin!(sck, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(mosi, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(miso, AfType::input(config.miso_pull)),
+            #[cfg(dma)]
+            None,
+            #[cfg(dma)]
+            None,
+            config,
+        )
+    }
+}
+
+#[cfg(dma)]
+impl<'d> UsartSpi<'d, Async> {
+    /// Create a new synchronous master driver using DMA.
+    pub fn new<T: Instance>(
+        peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        sck: Peri<'d, impl CkPin<T>>,
+        mosi: Peri<'d, impl TxPin<T>>,
+        miso: Peri<'d, impl RxPin<T>>,
+        tx_dma: Peri<'d, impl TxDma<T>>,
+        rx_dma: Peri<'d, impl RxDma<T>>,
+        config: SpiConfig,
+    ) -> Result<Self, ConfigError> {
+        let this = Self::new_inner(
+            peri,
+            new_pin!(sck, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(mosi, AfType::output(OutputType::PushPull, Speed::Medium)),
+            new_pin!(miso, AfType::input(config.miso_pull)),
+            new_dma!(tx_dma),
+            new_dma!(rx_dma),
+            config,
+        )?;
+        this.info.interrupt.unpend();
+        unsafe { this.info.interrupt.enable() };
+        Ok(this)
+    }
+
+    /// Write `data`, discarding the received bytes.
+    pub async fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        } else if data.len() > 0xFFFF {
+            return Err(Error::BufferTooLong);
+        }
+
+        let r = self.info.regs;
+        clear_rx(r);
+
+        let ch = self.tx_dma.as_mut().unwrap();
+        let transfer = unsafe { ch.write(data, tdr(r), Default::default()) };
+        r.cr3().modify(|w| w.set_dmat(true));
+        transfer.await;
+        r.cr3().modify(|w| w.set_dmat(false));
+
+        // The bytes clocked in meanwhile overran the receiver.
+        flush(self.info, self.state).await?;
+        clear_rx(r);
+        Ok(())
+    }
+
+    /// Read into `data`, writing zeroes.
+    pub async fn read(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        } else if data.len() > 0xFFFF {
+            return Err(Error::BufferTooLong);
+        }
+
+        let r = self.info.regs;
+        clear_rx(r);
+
+        let len = data.len();
+        let rx = self.rx_dma.as_mut().unwrap();
+        let rx = unsafe { rx.read(rdr(r), data, Default::default()) };
+        let tx = self.tx_dma.as_mut().unwrap();
+        let tx = unsafe { tx.write_repeated(&0u8, len, tdr(r), Default::default()) };
+
+        run_dma(r, rx, tx).await
+    }
+
+    /// Bidirectionally transfer by writing `write` and reading into `read` at the same time.
+    ///
+    /// If the lengths differ, extra bytes are written or read as in
+    /// [`embedded_hal_1::spi::SpiBus::transfer`].
+    pub async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        let common = read.len().min(write.len());
+        let (read, read_rest) = read.split_at_mut(common);
+        let (write, write_rest) = write.split_at(common);
+
+        if common > 0 {
+            if common > 0xFFFF {
+                return Err(Error::BufferTooLong);
+            }
+
+            let r = self.info.regs;
+            clear_rx(r);
+
+            let rx = self.rx_dma.as_mut().unwrap();
+            let rx = unsafe { rx.read(rdr(r), read, Default::default()) };
+            let tx = self.tx_dma.as_mut().unwrap();
+            let tx = unsafe { tx.write(write, tdr(r), Default::default()) };
+
+            run_dma(r, rx, tx).await?;
+        }
+
+        self.read(read_rest).await?;
+        self.write(write_rest).await
+    }
+
+    /// Bidirectionally transfer `data` in place.
+    pub async fn transfer_in_place(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        } else if data.len() > 0xFFFF {
+            return Err(Error::BufferTooLong);
+        }
+
+        let r = self.info.regs;
+        clear_rx(r);
+
+        // Each byte is read by the RX channel only after the TX channel has sent it.
+        let data = data as *mut [u8];
+        let rx = self.rx_dma.as_mut().unwrap();
+        let rx = unsafe { rx.read_raw(rdr(r), data, Default::default()) };
+        let tx = self.tx_dma.as_mut().unwrap();
+        let tx = unsafe { tx.write_raw(data, tdr(r), Default::default()) };
+
+        run_dma(r, rx, tx).await
+    }
+}
+
+/// Run an RX and a TX transfer of the same length to completion.
+#[cfg(dma)]
+async fn run_dma(r: Regs, rx: Transfer<'_>, tx: Transfer<'_>) -> Result<(), Error> {
+    // Enable the RX request first so that the first byte cannot be missed.
+    r.cr3().modify(|w| w.set_dmar(true));
+    r.cr3().modify(|w| w.set_dmat(true));
+    join(rx, tx).await;
+    r.cr3().modify(|w| {
+        w.set_dmar(false);
+        w.set_dmat(false);
+    });
+
+    if sr(r).read().ore() {
+        clear_rx(r);
+        return Err(Error::Overrun);
+    }
+    Ok(())
+}
+
+impl<'d, M: Mode> UsartSpi<'d, M> {
+    fn new_inner<T: Instance>(
+        _peri: Peri<'d, T>,
+        sck: Option<Peri<'d, AnyPin>>,
+        mosi: Option<Peri<'d, AnyPin>>,
+        miso: Option<Peri<'d, AnyPin>>,
+        #[cfg(dma)] tx_dma: Option<ChannelAndRequest<'d>>,
+        #[cfg(dma)] rx_dma: Option<ChannelAndRequest<'d>>,
+        config: SpiConfig,
+    ) -> Result<Self, ConfigError> {
+        let mut this = Self {
+            info: T::info(),
+            state: T::state(),
+            kernel_clock: T::frequency(),
+            sck,
+            mosi,
+            miso,
+            #[cfg(dma)]
+            tx_dma,
+            #[cfg(dma)]
+            rx_dma,
+            _phantom: PhantomData,
+        };
+
+        this.state.tx_rx_refcount.store(1, Ordering::Relaxed);
+        this.info.rcc.enable_and_reset();
+        this.info.regs.cr3().write(|_| {});
+        this.set_config(&config)?;
+
+        Ok(this)
+    }
+
+    /// Reconfigure the driver
+    pub fn set_config(&mut self, config: &SpiConfig) -> Result<(), ConfigError> {
+        let mut uart_config = Config::default();
+        uart_config.baudrate = config.frequency.0;
+        configure(self.info, self.kernel_clock, &uart_config, true, true)?;
+
+        // CPOL, CPHA and LBCL must not be written while the transmitter is enabled.
+        let r = self.info.regs;
+        r.cr1().modify(|w| {
+            w.set_ue(false);
+            w.set_te(false);
+        });
+        r.cr2().modify(|w| {
+            w.set_clken(true);
+            w.set_cpol(match config.mode.polarity {
+                Polarity::IdleLow => vals::Cpol::LOW,
+                Polarity::IdleHigh => vals::Cpol::HIGH,
+            });
+            w.set_cpha(match config.mode.phase {
+                Phase::CaptureOnFirstTransition => vals::Cpha::FIRST,
+                Phase::CaptureOnSecondTransition => vals::Cpha::SECOND,
+            });
+            w.set_lbcl(config.last_bit_clock);
+        });
+        r.cr1().modify(|w| {
+            w.set_ue(true);
+            w.set_te(true);
+        });
+
+        Ok(())
+    }
+
+    fn blocking_transfer_byte(&mut self, byte: u8) -> Result<u8, Error> {
+        let r = self.info.regs;
+
+        while !sr(r).read().txe() {}
+        unsafe { tdr(r).write_volatile(byte) };
+
+        loop {
+            let sr = sr(r).read();
+            if sr.ore() {
+                clear_rx(r);
+                return Err(Error::Overrun);
+            }
+            if sr.rxne() {
+                return Ok(unsafe { rdr(r).read_volatile() });
+            }
+        }
+    }
+
+    /// Blocking write, discarding the received bytes.
+    pub fn blocking_write(&mut self, data: &[u8]) -> Result<(), Error> {
+        clear_rx(self.info.regs);
+        for &b in data {
+            self.blocking_transfer_byte(b)?;
+        }
+        Ok(())
+    }
+
+    /// Blocking read, writing zeroes.
+    pub fn blocking_read(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        clear_rx(self.info.regs);
+        for b in data {
+            *b = self.blocking_transfer_byte(0)?;
+        }
+        Ok(())
+    }
+
+    /// Blocking bidirectional transfer.
+    ///
+    /// If the lengths differ, extra bytes are written or read as in
+    /// [`embedded_hal_1::spi::SpiBus::transfer`].
+    pub fn blocking_transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        clear_rx(self.info.regs);
+        let len = read.len().max(write.len());
+        for i in 0..len {
+            let b = self.blocking_transfer_byte(write.get(i).copied().unwrap_or(0))?;
+            if let Some(r) = read.get_mut(i) {
+                *r = b;
+            }
+        }
+        Ok(())
+    }
+
+    /// Blocking bidirectional transfer in place.
+    pub fn blocking_transfer_in_place(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        clear_rx(self.info.regs);
+        for b in data {
+            *b = self.blocking_transfer_byte(*b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Drop a stale received byte and clear the overrun flag.
+fn clear_rx(r: Regs) {
+    // On v1, reading DR after SR clears RXNE and the error flags.
+    let _ = sr(r).read();
+    unsafe { rdr(r).read_volatile() };
+}
+
+impl<'d, M: Mode> Drop for UsartSpi<'d, M> {
+    fn drop(&mut self) {
+        self.sck.as_ref().map(|x| x.set_as_disconnected());
+        self.mosi.as_ref().map(|x| x.set_as_disconnected());
+        self.miso.as_ref().map(|x| x.set_as_disconnected());
+        drop_tx_rx(self.info, self.state);
+    }
+}
+
+impl embedded_hal_1::spi::Error for Error {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        match *self {
+            Self::Overrun => embedded_hal_1::spi::ErrorKind::Overrun,
+            _ => embedded_hal_1::spi::ErrorKind::Other,
+        }
+    }
+}
+
+impl<'d, M: Mode> embedded_hal_1::spi::ErrorType for UsartSpi<'d, M> {
+    type Error = Error;
+}
+
+impl<'d, M: Mode> embedded_hal_1::spi::SpiBus<u8> for UsartSpi<'d, M> {
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        blocking_flush(self.info)
+    }
+
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.blocking_read(words)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.blocking_write(words)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.blocking_transfer(read, write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.blocking_transfer_in_place(words)
+    }
+}
+
+#[cfg(dma)]
+impl<'d> embedded_hal_async::spi::SpiBus<u8> for UsartSpi<'d, Async> {
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        // `write` only returns once the last byte is out.
+        Ok(())
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.write(words).await
+    }
+
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.read(words).await
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.transfer(read, write).await
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer_in_place(words).await
+    }
+}
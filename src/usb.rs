@@ -35,10 +35,11 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
 }
 
 fn init<T: Instance>() {
-    let freq = T::frequency();
-    if freq.0 != 48_000_000 {
-        panic!("USB clock (PLL) must be 48MHz");
-    }
+    let freq = unwrap!(
+        unsafe { crate::rcc::get_freqs() }.usb.to_hertz(),
+        "USB clock is not running, the PLL must run at 48MHz or 72MHz (see `config.rcc.usb`)"
+    );
+    assert!(freq.0 == 48_000_000, "USB clock must be 48MHz");
 
     T::Interrupt::unpend();
     unsafe { T::Interrupt::enable() };